@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+// Audience is the value of the `aud` (Audience) claim. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.3
+//
+// The claim is either a single case-sensitive string or an array of them. The form a token
+// arrived in is preserved, so re-serializing a parsed claim set emits the same shape.
+// Use `into_single`/`into_multiple` to force a particular output form.
+#[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Default for Audience {
+    fn default() -> Self {
+        Audience::Multiple(Vec::new())
+    }
+}
+
+impl Audience {
+    // Reports whether the claim carries no audience values at all.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Audience::Single(_) => false,
+            Audience::Multiple(v) => v.is_empty(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            Audience::Single(s) => std::slice::from_ref(s),
+            Audience::Multiple(v) => v,
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, String> {
+        self.as_slice().iter()
+    }
+
+    // Converts to the single string form. Fails, returning `self` unchanged, if there is not exactly one value.
+    pub fn into_single(self) -> Result<Self, Self> {
+        match self {
+            Audience::Multiple(mut v) if v.len() == 1 => Ok(Audience::Single(v.remove(0))),
+            Audience::Multiple(v) => Err(Audience::Multiple(v)),
+            single => Ok(single),
+        }
+    }
+
+    // Converts to the array form.
+    pub fn into_multiple(self) -> Self {
+        Audience::Multiple(self.into())
+    }
+}
+
+impl From<String> for Audience {
+    fn from(s: String) -> Self {
+        Audience::Single(s)
+    }
+}
+
+impl From<&str> for Audience {
+    fn from(s: &str) -> Self {
+        Audience::Single(s.to_string())
+    }
+}
+
+impl From<Vec<String>> for Audience {
+    fn from(v: Vec<String>) -> Self {
+        Audience::Multiple(v)
+    }
+}
+
+impl From<Vec<&str>> for Audience {
+    fn from(v: Vec<&str>) -> Self {
+        Audience::Multiple(v.into_iter().map(String::from).collect())
+    }
+}
+
+impl From<Audience> for Vec<String> {
+    fn from(aud: Audience) -> Self {
+        match aud {
+            Audience::Single(s) => vec![s],
+            Audience::Multiple(v) => v,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Audience {
+    type Item = &'a String;
+    type IntoIter = std::slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_wire_form() {
+        let single: Audience = serde_json::from_str(r#""api""#).unwrap();
+        assert_eq!(single, Audience::Single("api".to_string()));
+        assert_eq!(serde_json::to_string(&single).unwrap(), r#""api""#);
+
+        let multiple: Audience = serde_json::from_str(r#"["api","web"]"#).unwrap();
+        assert_eq!(multiple, Audience::from(vec!["api", "web"]));
+        assert_eq!(serde_json::to_string(&multiple).unwrap(), r#"["api","web"]"#);
+    }
+
+    #[test]
+    fn converts_between_forms() {
+        let single = Audience::from("api");
+        assert_eq!(single.clone().into_multiple(), Audience::from(vec!["api"]));
+        assert_eq!(Audience::from(vec!["api"]).into_single(), Ok(single));
+        assert!(Audience::from(vec!["api", "web"]).into_single().is_err());
+        assert!(Audience::default().is_empty());
+        assert_eq!(Audience::default().len(), 0);
+    }
+}
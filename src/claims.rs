@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{Clock, Leeway, RegisteredClaims, Validation, ValidationError};
+
+// Claims is a complete JWT Claims Set: the registered claims, a user-defined payload `T`
+// holding the private and public claims the application cares about, and every other claim
+// collected in `extra`. See https://datatracker.ietf.org/doc/html/rfc7519#section-4
+//
+// All three parts are flattened into one JSON object, so unknown claims survive a
+// deserialize/serialize round trip. `T` must not declare fields named after the
+// registered claims, since those are consumed by `registered` first.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Claims<T = ()> {
+    #[serde(flatten)]
+    pub registered: RegisteredClaims,
+
+    #[serde(flatten)]
+    pub custom: T,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl<T> Claims<T> {
+    pub fn new(registered: RegisteredClaims, custom: T) -> Self {
+        Claims {
+            registered,
+            custom,
+            extra: Map::new(),
+        }
+    }
+
+    pub fn valid(&self) -> Result<(), ValidationError> {
+        self.registered.valid()
+    }
+
+    pub fn valid_at(&self, clock: &dyn Clock, leeway: &Leeway) -> Result<(), ValidationError> {
+        self.registered.valid_at(clock, leeway)
+    }
+
+    pub fn validate(&self, validation: &Validation) -> Result<(), ValidationError> {
+        validation.validate(&self.registered)
+    }
+}
+
+impl<T> AsRef<RegisteredClaims> for Claims<T> {
+    fn as_ref(&self) -> &RegisteredClaims {
+        &self.registered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Profile {
+        name: String,
+        admin: bool,
+    }
+
+    #[test]
+    fn round_trips_custom_and_extra_claims() {
+        let input = r#"{"iss":"joe","exp":1300819380,"name":"John Doe","admin":true,"http://example.com/is_root":true,"scope":"openid"}"#;
+        let claims: Claims<Profile> = serde_json::from_str(input).unwrap();
+        assert_eq!(claims.registered.issuer, "joe");
+        assert_eq!(
+            claims.custom,
+            Profile {
+                name: "John Doe".to_string(),
+                admin: true
+            }
+        );
+        assert_eq!(claims.extra.len(), 2);
+        assert_eq!(claims.extra["scope"], "openid");
+
+        let output: Value = serde_json::to_value(&claims).unwrap();
+        assert_eq!(output, serde_json::from_str::<Value>(input).unwrap());
+    }
+
+    #[test]
+    fn keeps_unknown_claims_without_custom_payload() {
+        let claims: Claims = serde_json::from_str(r#"{"sub":"alice","roles":["admin"]}"#).unwrap();
+        assert_eq!(claims.registered.subject, "alice");
+        assert_eq!(claims.extra["roles"][0], "admin");
+        assert_eq!(serde_json::to_string(&claims).unwrap(), r#"{"sub":"alice","roles":["admin"]}"#);
+        assert!(claims.validate(&Validation::new().with_subject("alice").with_required_claims([])).is_ok());
+    }
+}
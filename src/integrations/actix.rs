@@ -0,0 +1,264 @@
+//! actix-web support: the `JwtAuth` middleware verifies the token of each request and stores its claims in the
+//! request extensions, where the `JwtClaims` extractor reads them. Both must name the same claims type.
+//!
+//! ```ignore
+//! async fn handler(JwtClaims(claims): JwtClaims<Claims>) -> String {
+//!     claims.registered.subject
+//! }
+//!
+//! let app = App::new().wrap(JwtAuth::<Claims>::new(auth)).route("/", web::get().to(handler));
+//! ```
+//!
+//! Without the middleware, `JwtClaims` verifies the token itself with the `Authenticator` registered as app data,
+//! either directly or as `web::Data<Authenticator>`.
+
+use std::{
+    any::Any,
+    fmt,
+    future::{ready, Future, Ready},
+    marker::PhantomData,
+    pin::Pin,
+};
+
+use actix_web::{
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorInternalServerError,
+    http::{
+        header::{AUTHORIZATION, COOKIE, WWW_AUTHENTICATE},
+        StatusCode,
+    },
+    web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError,
+};
+use serde::de::DeserializeOwned;
+
+use super::{cookie_value, query_value, Authenticator, Rejection, TokenSource};
+use crate::{RegisteredClaims, ValidationError};
+
+// JwtAuth is an actix-web middleware that verifies the token of each request with an `Authenticator`.
+//
+// On success the claims, of type `C`, are stored in the request extensions for `JwtClaims<C>`. Otherwise the
+// request fails with a `JwtRejection`.
+pub struct JwtAuth<C> {
+    authenticator: Authenticator,
+    claims: PhantomData<fn() -> C>,
+}
+
+impl<C> JwtAuth<C> {
+    pub fn new(authenticator: Authenticator) -> Self {
+        JwtAuth {
+            authenticator,
+            claims: PhantomData,
+        }
+    }
+}
+
+impl<C> fmt::Debug for JwtAuth<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtAuth").field("authenticator", &self.authenticator).finish()
+    }
+}
+
+impl<S, B, C> Transform<S, ServiceRequest> for JwtAuth<C>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    C: DeserializeOwned + AsRef<RegisteredClaims> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = JwtAuthMiddleware<S, C>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(JwtAuthMiddleware {
+            service,
+            authenticator: self.authenticator.clone(),
+            claims: PhantomData,
+        }))
+    }
+}
+
+// JwtAuthMiddleware is the service `JwtAuth` wraps around the rest of the application.
+pub struct JwtAuthMiddleware<S, C> {
+    service: S,
+    authenticator: Authenticator,
+    claims: PhantomData<fn() -> C>,
+}
+
+impl<S, B, C> Service<ServiceRequest> for JwtAuthMiddleware<S, C>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    C: DeserializeOwned + AsRef<RegisteredClaims> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match self.authenticator.authenticate::<C>(|source| lookup(source, req.request())) {
+            Ok(token) => {
+                req.extensions_mut().insert(Authenticated(Box::new(token.claims)));
+                Box::pin(self.service.call(req))
+            }
+            Err(err) => Box::pin(ready(Err(JwtRejection(err).into()))),
+        }
+    }
+}
+
+// Authenticated holds the claims stored by `JwtAuth`, whatever their type, so `JwtClaims` can tell that the
+// middleware has run even when it verified the token as another type.
+struct Authenticated(Box<dyn Any>);
+
+// JwtClaims extracts the verified and validated claims of a request, either `RegisteredClaims` or a type
+// embedding them. They are taken from the request extensions when `JwtAuth` has run, in which case it fails
+// with 500 Internal Server Error if the middleware verified the token as another type, rather than verify the
+// token twice. Without the middleware, the token is verified with the `Authenticator` in the app data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtClaims<C = RegisteredClaims>(pub C);
+
+impl<C> FromRequest for JwtClaims<C>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims> + Clone + 'static,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        if let Some(Authenticated(claims)) = req.extensions().get::<Authenticated>() {
+            return ready(match claims.downcast_ref::<C>() {
+                Some(claims) => Ok(JwtClaims(claims.clone())),
+                None => Err(ErrorInternalServerError("JwtAuth verified the token as another type than JwtClaims")),
+            });
+        }
+        let authenticator = req
+            .app_data::<Authenticator>()
+            .or_else(|| req.app_data::<web::Data<Authenticator>>().map(|data| data.as_ref()));
+        let Some(authenticator) = authenticator else {
+            return ready(Err(ErrorInternalServerError("no Authenticator is registered as app data")));
+        };
+        let result = authenticator.authenticate::<C>(|source| lookup(source, req));
+        ready(result.map(|token| JwtClaims(token.claims)).map_err(|err| JwtRejection(err).into()))
+    }
+}
+
+// Looks up the raw value of `source` in an actix-web request.
+fn lookup(source: &TokenSource, req: &HttpRequest) -> Option<String> {
+    match source {
+        TokenSource::Bearer => req.headers().get(AUTHORIZATION)?.to_str().ok().map(str::to_string),
+        TokenSource::Cookie(name) => req
+            .headers()
+            .get_all(COOKIE)
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| cookie_value(value, name))
+            .map(str::to_string),
+        TokenSource::Query(name) => query_value(req.query_string(), name).map(str::to_string),
+    }
+}
+
+// JwtRejection is the error `JwtAuth` and `JwtClaims` fail with, rendered as `integrations::Rejection` describes,
+// with the error's reason as the body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtRejection(ValidationError);
+
+impl JwtRejection {
+    pub fn error(&self) -> &ValidationError {
+        &self.0
+    }
+}
+
+impl fmt::Display for JwtRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for JwtRejection {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(Rejection::new(&self.0).status).unwrap_or(StatusCode::UNAUTHORIZED)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let Some(challenge) = Rejection::new(&self.0).challenge {
+            response.insert_header((WWW_AUTHENTICATE, challenge));
+        }
+        response.body(self.0.reason())
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::integrations::fixtures::{authenticator, token};
+
+    async fn subject(JwtClaims(claims): JwtClaims) -> String {
+        claims.subject
+    }
+
+    #[actix_web::test]
+    async fn stores_claims_in_extensions() {
+        let app = test::init_service(
+            App::new()
+                .wrap(JwtAuth::<RegisteredClaims>::new(authenticator()))
+                .route("/", web::get().to(subject)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().insert_header((AUTHORIZATION, format!("Bearer {}", token("joe", ""))));
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(test::read_body(response).await, "alice");
+
+        let request = test::TestRequest::get().insert_header((COOKIE, format!("session={}", token("joe", ""))));
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = test::TestRequest::get().insert_header((AUTHORIZATION, format!("Bearer {}", token("mallory", ""))));
+        let err = test::try_call_service(&app, request.to_request()).await.unwrap_err();
+        let response = err.error_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers().get(WWW_AUTHENTICATE).unwrap(),
+            r#"Bearer error="invalid_token", error_description="invalid_issuer""#
+        );
+
+        let err = test::try_call_service(&app, test::TestRequest::get().to_request()).await.unwrap_err();
+        assert_eq!(err.as_error::<JwtRejection>().unwrap().error(), &ValidationError::MissingToken);
+    }
+
+    #[actix_web::test]
+    async fn fails_when_the_middleware_verified_another_type() {
+        // the authenticator in the app data would accept the token, but is not consulted once the middleware ran.
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(authenticator()))
+                .wrap(JwtAuth::<crate::Claims>::new(authenticator()))
+                .route("/", web::get().to(subject)),
+        )
+        .await;
+        let request = test::TestRequest::get().insert_header((AUTHORIZATION, format!("Bearer {}", token("joe", ""))));
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn extracts_claims_without_middleware() {
+        let app = test::init_service(App::new().app_data(web::Data::new(authenticator())).route("/", web::get().to(subject))).await;
+        let request = test::TestRequest::get().insert_header((AUTHORIZATION, format!("Bearer {}", token("joe", ""))));
+        assert_eq!(test::call_and_read_body(&app, request.to_request()).await, "alice");
+
+        let response = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let app = test::init_service(App::new().route("/", web::get().to(subject))).await;
+        let request = test::TestRequest::get().insert_header((AUTHORIZATION, format!("Bearer {}", token("joe", ""))));
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
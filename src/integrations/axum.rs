@@ -0,0 +1,155 @@
+//! axum support: the `JwtClaims` extractor verifies the token of a request with the `Authenticator` taken
+//! from the router state.
+//!
+//! ```ignore
+//! #[derive(Clone, FromRef)]
+//! struct AppState {
+//!     auth: Authenticator,
+//! }
+//!
+//! async fn handler(JwtClaims(claims): JwtClaims<Claims>) -> String {
+//!     claims.registered.subject
+//! }
+//!
+//! let app = Router::new().route("/", get(handler)).with_state(AppState { auth });
+//! ```
+
+use std::fmt;
+
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{
+        header::{HeaderValue, WWW_AUTHENTICATE},
+        request::Parts,
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+
+use super::{lookup_http, Authenticator, Rejection};
+use crate::{RegisteredClaims, ValidationError};
+
+// JwtClaims extracts the verified and validated claims of a request, either `RegisteredClaims` or a type
+// embedding them. The keys, policy and token sources come from the `Authenticator` in the router state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtClaims<C = RegisteredClaims>(pub C);
+
+impl<S, C> FromRequestParts<S> for JwtClaims<C>
+where
+    Authenticator: FromRef<S>,
+    S: Send + Sync,
+    C: DeserializeOwned + AsRef<RegisteredClaims> + Send,
+{
+    type Rejection = JwtRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let authenticator = Authenticator::from_ref(state);
+        let token = authenticator.authenticate(|source| lookup_http(source, &parts.headers, &parts.uri))?;
+        Ok(JwtClaims(token.claims))
+    }
+}
+
+// JwtRejection is returned when `JwtClaims` cannot be extracted. It responds as `integrations::Rejection` describes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtRejection(ValidationError);
+
+impl JwtRejection {
+    pub fn error(&self) -> &ValidationError {
+        &self.0
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(Rejection::new(&self.0).status).unwrap_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+impl From<ValidationError> for JwtRejection {
+    fn from(err: ValidationError) -> Self {
+        JwtRejection(err)
+    }
+}
+
+impl fmt::Display for JwtRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for JwtRejection {}
+
+impl IntoResponse for JwtRejection {
+    fn into_response(self) -> Response {
+        let rejection = Rejection::new(&self.0);
+        let mut response = (self.status(), self.0.reason()).into_response();
+        if let Some(value) = rejection.challenge.and_then(|challenge| HeaderValue::from_str(&challenge).ok()) {
+            response.headers_mut().insert(WWW_AUTHENTICATE, value);
+        }
+        response
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use super::*;
+    use crate::{
+        integrations::fixtures::{authenticator, token},
+        Claims,
+    };
+    use axum::http::Request;
+
+    #[derive(Clone)]
+    struct AppState {
+        auth: Authenticator,
+    }
+
+    impl FromRef<AppState> for Authenticator {
+        fn from_ref(state: &AppState) -> Self {
+            state.auth.clone()
+        }
+    }
+
+    fn state() -> AppState {
+        AppState {
+            auth: authenticator().with_required_scopes(["read"]),
+        }
+    }
+
+    async fn extract(request: Request<()>) -> Result<JwtClaims<Claims>, JwtRejection> {
+        let (mut parts, ()) = request.into_parts();
+        JwtClaims::from_request_parts(&mut parts, &state()).await
+    }
+
+    #[tokio::test]
+    async fn extracts_validated_claims() {
+        let request = Request::builder().header("Authorization", format!("Bearer {}", token("joe", "read write")));
+        let JwtClaims(claims) = extract(request.body(()).unwrap()).await.unwrap();
+        assert_eq!(claims.registered.subject, "alice");
+
+        let request = Request::builder().uri(format!("/orders?access_token={}", token("joe", "read")));
+        let JwtClaims(claims) = extract(request.body(()).unwrap()).await.unwrap();
+        assert_eq!(claims.extra["scope"], "read");
+    }
+
+    #[tokio::test]
+    async fn rejects_with_401_and_403() {
+        let rejection = extract(Request::new(())).await.unwrap_err();
+        assert_eq!(rejection.error(), &ValidationError::MissingToken);
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+
+        let request = Request::builder().header("Authorization", format!("Bearer {}x", token("joe", "read")));
+        let response = extract(request.body(()).unwrap()).await.unwrap_err().into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            r#"Bearer error="invalid_token", error_description="invalid_signature""#
+        );
+
+        let request = Request::builder().header("Authorization", format!("Bearer {}", token("joe", "write")));
+        let response = extract(request.body(()).unwrap()).await.unwrap_err().into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], r#"Bearer error="insufficient_scope", scope="read""#);
+    }
+}
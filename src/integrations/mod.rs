@@ -0,0 +1,488 @@
+//! Request authentication for web frameworks, each behind the cargo feature named after it.
+//!
+//! An `Authenticator` holds what every integration needs: where a request carries its token, the keys
+//! to verify it with, the `Validation` policy for its claims and the scopes or roles it must grant. The framework modules only adapt it:
+//! `salvo` provides the `JwtAuth` hoop and its OpenAPI security scheme, `axum` the `JwtClaims` extractor,
+//! `actix` the `JwtAuth` middleware and `JwtClaims` extractor, `tower` the `JwtLayer` for any `http` service, and
+//! `tonic` the `JwtInterceptor` for gRPC services.
+
+use std::{fmt, sync::Arc};
+
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Deserialize, Deserializer,
+};
+
+use crate::{
+    jws::{KeyProvider, TokenData},
+    Clock, RegisteredClaims, SystemClock, Validation, ValidationError,
+};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "salvo")]
+pub mod salvo;
+#[cfg(feature = "tonic")]
+pub mod tonic;
+#[cfg(feature = "tower")]
+pub mod tower;
+
+// TokenSource is a place in an HTTP request where a token may be found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    // the `Authorization` header with the `Bearer` scheme. See https://datatracker.ietf.org/doc/html/rfc6750#section-2.1
+    Bearer,
+    // the cookie with the given name.
+    Cookie(String),
+    // the query parameter with the given name. See https://datatracker.ietf.org/doc/html/rfc6750#section-2.3
+    Query(String),
+}
+
+// Authenticator verifies the token carried by a request and validates its claims.
+//
+// By default it reads the token from the `Authorization` header only, validates with `Validation::new()`,
+// requires no scopes or roles and reads the time from `SystemClock`. Revoked and replayed tokens are detected by the
+// `Validation`, see `Validation::with_revocation_list` and `Validation::with_replay_store`.
+#[derive(Clone)]
+pub struct Authenticator {
+    keys: Arc<dyn KeyProvider>,
+    validation: Validation,
+    sources: Vec<TokenSource>,
+    scopes: Vec<String>,
+    roles: Vec<String>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Authenticator {
+    // Creates an authenticator verifying tokens with `keys`, e.g. a `VerifyingKey`, `JwkSet` or `CachedJwks`.
+    pub fn new(keys: impl KeyProvider + 'static) -> Self {
+        Authenticator {
+            keys: Arc::new(keys),
+            validation: Validation::new(),
+            sources: vec![TokenSource::Bearer],
+            scopes: Vec::new(),
+            roles: Vec::new(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    // Replaces the policy the claims are validated against.
+    pub fn with_validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    // Replaces the places a token is looked for. They are tried in order, and the first one present is used.
+    pub fn with_sources(mut self, sources: impl IntoIterator<Item = TokenSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    // Requires every token to grant all of `scopes`, through its space-delimited `scope` claim or its `scp` claim.
+    // See https://datatracker.ietf.org/doc/html/rfc8693#section-4.2
+    pub fn with_required_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // Requires every token to list all of `roles` in its `roles` claim, failing with `MissingRole` otherwise.
+    pub fn with_required_roles(mut self, roles: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.roles = roles.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // Reads the current time from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn validation(&self) -> &Validation {
+        &self.validation
+    }
+
+    pub fn sources(&self) -> &[TokenSource] {
+        &self.sources
+    }
+
+    pub fn required_scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    pub fn required_roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    // Verifies `token`, validates its claims and checks that it grants the required scopes and roles. The replay
+    // store of the validation, if any, is consulted last.
+    pub fn verify<C>(&self, token: &str) -> Result<TokenData<C>, ValidationError>
+    where
+        C: DeserializeOwned + AsRef<RegisteredClaims>,
+    {
+        if self.scopes.is_empty() && self.roles.is_empty() {
+            return self.keys.verify_at(token, &self.validation, &*self.clock);
+        }
+        // the replay store consumes the token, so a token lacking a grant must not reach it.
+        let verified: TokenData<Granted<C>> = match &self.validation.replay_store {
+            Some(_) => {
+                let validation = Validation {
+                    replay_store: None,
+                    ..self.validation.clone()
+                };
+                self.keys.verify_at(token, &validation, &*self.clock)?
+            }
+            None => self.keys.verify_at(token, &self.validation, &*self.clock)?,
+        };
+        let Granted { claims, grants } = verified.claims;
+        let scopes: Vec<&str> = grants.scope.iter().chain(&grants.scp).flat_map(Grant::iter).collect();
+        let roles: Vec<&str> = grants.roles.iter().flat_map(Grant::iter).collect();
+        if let Some(scope) = self.scopes.iter().find(|scope| !scopes.contains(&scope.as_str())) {
+            return Err(ValidationError::InsufficientScope(scope.clone()));
+        }
+        if let Some(role) = self.roles.iter().find(|role| !roles.contains(&role.as_str())) {
+            return Err(ValidationError::MissingRole(role.clone()));
+        }
+        if let Some(store) = &self.validation.replay_store {
+            store.check_at(claims.as_ref(), &self.validation.leeway, &*self.clock)?;
+        }
+        Ok(TokenData {
+            header: verified.header,
+            claims,
+        })
+    }
+
+    // Finds the token of a request with `lookup`, which returns the raw value of a source if the request has it.
+    // For `TokenSource::Bearer` that is the whole `Authorization` header, whose scheme is checked here.
+    pub(crate) fn find_token(&self, mut lookup: impl FnMut(&TokenSource) -> Option<String>) -> Result<String, ValidationError> {
+        self.sources
+            .iter()
+            .find_map(|source| {
+                let value = lookup(source)?;
+                match source {
+                    TokenSource::Bearer => bearer_token(&value).map(str::to_string),
+                    _ => Some(value).filter(|value| !value.is_empty()),
+                }
+            })
+            .ok_or(ValidationError::MissingToken)
+    }
+
+    // Finds, verifies and validates the token of a request, as `find_token` and `verify` do.
+    pub(crate) fn authenticate<C>(&self, lookup: impl FnMut(&TokenSource) -> Option<String>) -> Result<TokenData<C>, ValidationError>
+    where
+        C: DeserializeOwned + AsRef<RegisteredClaims>,
+    {
+        self.verify(&self.find_token(lookup)?)
+    }
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("validation", &self.validation)
+            .field("sources", &self.sources)
+            .field("scopes", &self.scopes)
+            .field("roles", &self.roles)
+            .finish_non_exhaustive()
+    }
+}
+
+// Granted holds the claims of a token along with its grants, both read from the same payload.
+struct Granted<C> {
+    claims: C,
+    grants: Grants,
+}
+
+impl<C> AsRef<RegisteredClaims> for Granted<C>
+where
+    C: AsRef<RegisteredClaims>,
+{
+    fn as_ref(&self) -> &RegisteredClaims {
+        self.claims.as_ref()
+    }
+}
+
+impl<'de, C: DeserializeOwned> Deserialize<'de> for Granted<C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // the payload is parsed once, then read as each type, so `C` and `Grants` may share claims.
+        let payload = serde_json::Value::deserialize(deserializer)?;
+        Ok(Granted {
+            claims: C::deserialize(&payload).map_err(D::Error::custom)?,
+            grants: Grants::deserialize(&payload).map_err(D::Error::custom)?,
+        })
+    }
+}
+
+// Grants are the claims that scopes and roles are read from.
+#[derive(Deserialize)]
+struct Grants {
+    #[serde(default)]
+    scope: Option<Grant>,
+    #[serde(default)]
+    scp: Option<Grant>,
+    #[serde(default)]
+    roles: Option<Grant>,
+}
+
+// Grant is a claim holding either a space-delimited string or an array of strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Grant {
+    Delimited(String),
+    List(Vec<String>),
+}
+
+impl Grant {
+    fn iter(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Grant::Delimited(value) => Box::new(value.split_ascii_whitespace()),
+            Grant::List(values) => Box::new(values.iter().map(String::as_str)),
+        }
+    }
+}
+
+// Extracts the token from an `Authorization` header value using the `Bearer` scheme, whose name is case-insensitive.
+pub(crate) fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("Bearer") && !token.is_empty()).then_some(token)
+}
+
+// Looks up the raw value of `source` in the parts of an `http` request.
+#[cfg(any(feature = "axum", feature = "tower"))]
+pub(crate) fn lookup_http(source: &TokenSource, headers: &http::HeaderMap, uri: &http::Uri) -> Option<String> {
+    match source {
+        TokenSource::Bearer => headers.get(http::header::AUTHORIZATION)?.to_str().ok().map(str::to_string),
+        TokenSource::Cookie(name) => headers
+            .get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| cookie_value(value, name))
+            .map(str::to_string),
+        TokenSource::Query(name) => query_value(uri.query()?, name).map(str::to_string),
+    }
+}
+
+// Finds the cookie called `name` in a `Cookie` header value. See https://datatracker.ietf.org/doc/html/rfc6265#section-5.4
+#[cfg(any(feature = "axum", feature = "actix", feature = "tower"))]
+pub(crate) fn cookie_value<'a>(cookie: &'a str, name: &str) -> Option<&'a str> {
+    cookie
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find_map(|(key, value)| (key == name).then(|| value.trim_matches('"')))
+}
+
+// Finds the parameter called `name` in a query string. The value is used as is, since JWTs only contain
+// URL-safe characters.
+#[cfg(any(feature = "axum", feature = "actix", feature = "tower"))]
+pub(crate) fn query_value<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(key, value)| (key == name).then_some(value))
+}
+
+// Rejection is how every integration answers a failed authentication over HTTP: 401 Unauthorized with a Bearer
+// challenge, 403 Forbidden with an `insufficient_scope` challenge if a required scope or role is missing, or 503
+// Service Unavailable without a challenge if the key set cannot be loaded. See https://datatracker.ietf.org/doc/html/rfc6750#section-3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Rejection {
+    pub(crate) status: u16,
+    pub(crate) challenge: Option<String>,
+}
+
+impl Rejection {
+    pub(crate) fn new(err: &ValidationError) -> Self {
+        match err {
+            // the request carried no credentials, so the challenge names no error.
+            ValidationError::MissingToken => Rejection {
+                status: 401,
+                challenge: Some("Bearer".to_string()),
+            },
+            // the token is valid, but does not allow this request. See https://datatracker.ietf.org/doc/html/rfc6750#section-3.1
+            ValidationError::InsufficientScope(scope) => Rejection {
+                status: 403,
+                challenge: Some(format!(r#"Bearer error="insufficient_scope", scope="{scope}""#)),
+            },
+            // roles are not OAuth scopes, so the client is not told to request one.
+            ValidationError::MissingRole(_) => Rejection {
+                status: 403,
+                challenge: Some(format!(r#"Bearer error="insufficient_scope", error_description="{}""#, err.reason())),
+            },
+            // the token may be fine, but it cannot be checked right now.
+            ValidationError::KeySetUnavailable(_) => Rejection {
+                status: 503,
+                challenge: None,
+            },
+            _ => Rejection {
+                status: 401,
+                challenge: Some(format!(r#"Bearer error="invalid_token", error_description="{}""#, err.reason())),
+            },
+        }
+    }
+}
+
+// The fixtures shared by the tests of the integrations.
+#[cfg(all(test, feature = "jws-hmac"))]
+pub(crate) mod fixtures {
+    use chrono::{TimeZone as _, Utc};
+
+    use super::{Authenticator, TokenSource};
+    use crate::{
+        jws::{sign, Algorithm, Header, SigningKey, VerifyingKey},
+        Claims, FixedClock, Validation,
+    };
+
+    // Returns an authenticator for the tokens of `token`, read from the `Authorization` header, the `session`
+    // cookie or the `access_token` query parameter, on 2024-01-01.
+    pub(crate) fn authenticator() -> Authenticator {
+        Authenticator::new(VerifyingKey::from_hmac_secret(&[7; 32]))
+            .with_validation(Validation::new().with_issuer("joe"))
+            .with_sources([
+                TokenSource::Bearer,
+                TokenSource::Cookie("session".to_string()),
+                TokenSource::Query("access_token".to_string()),
+            ])
+            .with_clock(FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()))
+    }
+
+    // Signs a token from `issuer` for `alice`, granting `scope` and expiring on 2024-01-02.
+    pub(crate) fn token(issuer: &str, scope: &str) -> String {
+        let claims: Claims = serde_json::from_value(serde_json::json!({ "iss": issuer, "sub": "alice", "exp": 1704153600, "scope": scope })).unwrap();
+        sign(&Header::new(Algorithm::HS256), &claims, &SigningKey::from_hmac_secret(&[7; 32])).unwrap()
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use chrono::{TimeZone as _, Utc};
+
+    use super::{
+        fixtures::{authenticator, token},
+        *,
+    };
+    use crate::jws::{sign, Algorithm, Header, SigningKey};
+
+    #[test]
+    fn parses_bearer_credentials() {
+        assert_eq!(bearer_token("Bearer abc.def.ghi"), Some("abc.def.ghi"));
+        assert_eq!(bearer_token("bearer  abc"), Some("abc"));
+        assert_eq!(bearer_token("Basic dXNlcjpwYXNz"), None);
+        assert_eq!(bearer_token("Bearer "), None);
+        assert_eq!(bearer_token("Bearer"), None);
+    }
+
+    #[test]
+    fn finds_tokens_in_configured_order() {
+        let authenticator = authenticator().with_sources([TokenSource::Bearer, TokenSource::Cookie("session".to_string())]);
+        let from_cookie = |source: &TokenSource| match source {
+            TokenSource::Bearer => Some("Basic dXNlcjpwYXNz".to_string()),
+            TokenSource::Cookie(name) if name == "session" => Some(token("joe", "")),
+            _ => None,
+        };
+        let verified: TokenData<RegisteredClaims> = authenticator.authenticate(from_cookie).unwrap();
+        assert_eq!(verified.claims.issuer, "joe");
+
+        let err = authenticator
+            .find_token(|source| matches!(source, TokenSource::Query(_)).then(|| token("joe", "")))
+            .unwrap_err();
+        assert_eq!(err, ValidationError::MissingToken);
+
+        let err = authenticator
+            .authenticate::<RegisteredClaims>(|_| Some(format!("Bearer {}", token("mallory", ""))))
+            .unwrap_err();
+        assert_eq!(err.reason(), "invalid_issuer");
+    }
+
+    #[test]
+    fn rejects_replayed_tokens() {
+        let validation = Validation::new().with_issuer("joe").with_replay_store(crate::MemoryReplayStore::new());
+        let authenticator = authenticator().with_validation(validation).with_required_scopes(["invite"]);
+        let token = |scope: &str| {
+            let claims: crate::Claims = serde_json::from_value(serde_json::json!({
+                "iss": "joe", "jti": "invite-1", "exp": 1704153600, "scope": scope,
+            }))
+            .unwrap();
+            sign(&Header::new(Algorithm::HS256), &claims, &SigningKey::from_hmac_secret(&[7; 32])).unwrap()
+        };
+
+        // a token lacking a grant is rejected without being recorded as used.
+        let err = authenticator.verify::<RegisteredClaims>(&token("read")).unwrap_err();
+        assert_eq!(err, ValidationError::InsufficientScope("invite".to_string()));
+        assert!(authenticator.verify::<RegisteredClaims>(&token("invite")).is_ok());
+        let err = authenticator.clone().verify::<RegisteredClaims>(&token("invite")).unwrap_err();
+        assert_eq!(err, ValidationError::TokenReplayed("invite-1".to_string()));
+    }
+
+    #[test]
+    fn rejects_revoked_tokens() {
+        let revocations = Arc::new(crate::MemoryRevocationList::new());
+        let validation = Validation::new().with_issuer("joe").with_revocation_list(revocations.clone());
+        let authenticator = authenticator().with_validation(validation);
+        assert!(authenticator.verify::<RegisteredClaims>(&token("joe", "")).is_ok());
+
+        revocations.revoke_issuer("joe");
+        let err = authenticator.verify::<RegisteredClaims>(&token("joe", "")).unwrap_err();
+        assert_eq!(err, ValidationError::TokenRevoked(crate::Claim::Issuer));
+    }
+
+    #[test]
+    fn requires_scopes_and_roles() {
+        let authenticator = authenticator().with_required_scopes(["read", "write"]).with_required_roles(["admin"]);
+        let claims = |grants: &str| {
+            let mut claims: crate::Claims = serde_json::from_str(grants).unwrap();
+            claims.registered.issuer = "joe".to_string();
+            claims.registered.expires_at = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).single();
+            sign(&Header::new(Algorithm::HS256), &claims, &SigningKey::from_hmac_secret(&[7; 32])).unwrap()
+        };
+
+        let granted = claims(r#"{"scope":"read write","roles":["admin"]}"#);
+        let verified: TokenData<crate::Claims> = authenticator.verify(&granted).unwrap();
+        assert_eq!(verified.claims.extra["scope"], "read write");
+        authenticator
+            .verify::<RegisteredClaims>(&claims(r#"{"scp":["write","read"],"roles":"admin"}"#))
+            .unwrap();
+
+        for (grants, missing) in [(r#"{"scope":"read","roles":["admin"]}"#, "write"), ("{}", "read")] {
+            let err = authenticator.verify::<RegisteredClaims>(&claims(grants)).unwrap_err();
+            assert_eq!(err, ValidationError::InsufficientScope(missing.to_string()), "{grants}");
+        }
+        let err = authenticator
+            .verify::<RegisteredClaims>(&claims(r#"{"scope":"read write","roles":"user"}"#))
+            .unwrap_err();
+        assert_eq!(err, ValidationError::MissingRole("admin".to_string()));
+    }
+
+    #[cfg(any(feature = "axum", feature = "actix", feature = "tower"))]
+    #[test]
+    fn reads_cookies_and_query_parameters() {
+        assert_eq!(cookie_value("theme=dark; session=abc.def.ghi", "session"), Some("abc.def.ghi"));
+        assert_eq!(cookie_value("session=\"abc\"", "session"), Some("abc"));
+        assert_eq!(cookie_value("my_session=abc", "session"), None);
+        assert_eq!(query_value("page=2&access_token=abc.def&x", "access_token"), Some("abc.def"));
+        assert_eq!(query_value("page=2", "access_token"), None);
+    }
+
+    #[test]
+    fn maps_failures_to_rejections() {
+        let rejection = Rejection::new(&ValidationError::MissingToken);
+        assert_eq!((rejection.status, rejection.challenge.as_deref()), (401, Some("Bearer")));
+        let rejection = Rejection::new(&ValidationError::InvalidSignature);
+        assert_eq!(
+            rejection.challenge.as_deref(),
+            Some(r#"Bearer error="invalid_token", error_description="invalid_signature""#)
+        );
+        let rejection = Rejection::new(&ValidationError::InsufficientScope("write".to_string()));
+        assert_eq!(
+            (rejection.status, rejection.challenge.as_deref()),
+            (403, Some(r#"Bearer error="insufficient_scope", scope="write""#))
+        );
+        let rejection = Rejection::new(&ValidationError::MissingRole("admin".to_string()));
+        assert_eq!(
+            (rejection.status, rejection.challenge.as_deref()),
+            (403, Some(r#"Bearer error="insufficient_scope", error_description="missing_role""#))
+        );
+        assert_eq!(Rejection::new(&ValidationError::KeySetUnavailable("timeout".to_string())).status, 503);
+    }
+}
@@ -0,0 +1,264 @@
+//! Salvo support: the `JwtAuth` hoop authenticates requests and inserts their claims into the `Depot`.
+//!
+//! Protecting a router with `JwtRouterExt::jwt_auth` also documents its security requirement on its endpoints:
+//! `bearerAuth` with the required scopes, and `bearerRoles` with the required roles, if any.
+//! `OpenApiExt::bearer_auth` registers both schemes.
+//!
+//! ```ignore
+//! let auth = JwtAuth::<Claims>::new(Authenticator::new(keys).with_required_scopes(["orders:read"]));
+//! let router = Router::new().jwt_auth(auth).get(handler);
+//! let doc = OpenApi::new("orders", "1.0").bearer_auth().merge_router(&router);
+//!
+//! #[handler]
+//! async fn handler(depot: &mut Depot) -> String {
+//!     let claims = depot.get_typed::<Claims>().unwrap();
+//!     claims.registered.subject.clone()
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use salvo::{
+    async_trait,
+    http::{
+        header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+        StatusCode,
+    },
+    oapi::{
+        security::{Http, HttpAuthScheme, SecurityRequirement, SecurityScheme},
+        OpenApi, RouterExt as _,
+    },
+    Depot, FlowCtrl, Handler, Request, Response, Router,
+};
+use serde::de::DeserializeOwned;
+
+use super::{Authenticator, Rejection, TokenSource};
+use crate::{RegisteredClaims, ValidationError};
+
+// BEARER_AUTH is the name the JWT security scheme is registered under in the OpenAPI document.
+pub const BEARER_AUTH: &str = "bearerAuth";
+
+// BEARER_ROLES is the name the roles required of the same JWT are documented under. For a scheme other than
+// OAuth 2.0, a security requirement lists role names. See https://spec.openapis.org/oas/v3.1.0#security-requirement-object
+pub const BEARER_ROLES: &str = "bearerRoles";
+
+// Returns the HTTP Bearer security scheme for JWTs. See https://spec.openapis.org/oas/v3.1.0#security-scheme-object
+pub fn bearer_security_scheme() -> SecurityScheme {
+    SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer).bearer_format("JWT"))
+}
+
+// Returns the security scheme the roles of a JWT are documented with: the same Bearer token, read for its
+// `roles` claim.
+pub fn bearer_roles_security_scheme() -> SecurityScheme {
+    let http = Http::new(HttpAuthScheme::Bearer).bearer_format("JWT");
+    SecurityScheme::Http(http.description("The bearer JWT must list the required roles in its `roles` claim."))
+}
+
+// JwtAuth is a Salvo hoop that verifies the token of each request with an `Authenticator`.
+//
+// On success the claims, of type `C`, are inserted into the `Depot` and can be read with `depot.get_typed::<C>()`.
+// Otherwise the rest of the chain is skipped and the request is answered as `integrations::Rejection` describes.
+pub struct JwtAuth<C> {
+    authenticator: Authenticator,
+    claims: PhantomData<fn() -> C>,
+}
+
+impl<C> JwtAuth<C> {
+    pub fn new(authenticator: Authenticator) -> Self {
+        JwtAuth {
+            authenticator,
+            claims: PhantomData,
+        }
+    }
+
+    pub fn authenticator(&self) -> &Authenticator {
+        &self.authenticator
+    }
+
+    // Returns the security requirement of the endpoints this hoop protects: `bearerAuth`, listing the required
+    // scopes, and `bearerRoles`, listing the required roles, if there are any.
+    pub fn security_requirement(&self) -> SecurityRequirement {
+        let requirement = SecurityRequirement::new(BEARER_AUTH, self.authenticator.required_scopes());
+        match self.authenticator.required_roles() {
+            [] => requirement,
+            roles => requirement.add(BEARER_ROLES, roles),
+        }
+    }
+}
+
+impl<C> std::fmt::Debug for JwtAuth<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtAuth").field("authenticator", &self.authenticator).finish()
+    }
+}
+
+#[async_trait]
+impl<C> Handler for JwtAuth<C>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims> + Send + Sync + 'static,
+{
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let result = self.authenticator.authenticate::<C>(|source| match source {
+            TokenSource::Bearer => req.headers().get(AUTHORIZATION)?.to_str().ok().map(str::to_string),
+            TokenSource::Cookie(name) => req.cookie(name).map(|cookie| cookie.value().to_string()),
+            TokenSource::Query(name) => req.query::<String>(name),
+        });
+        match result {
+            Ok(token) => {
+                depot.insert_typed(token.claims);
+            }
+            Err(err) => {
+                reject(res, &err);
+                ctrl.skip_rest();
+            }
+        }
+    }
+}
+
+fn reject(res: &mut Response, err: &ValidationError) {
+    let rejection = Rejection::new(err);
+    res.status_code(StatusCode::from_u16(rejection.status).unwrap_or(StatusCode::UNAUTHORIZED));
+    if let Some(value) = rejection.challenge.and_then(|challenge| HeaderValue::from_str(&challenge).ok()) {
+        res.headers_mut().insert(WWW_AUTHENTICATE, value);
+    }
+}
+
+// OpenApiExt registers the JWT security schemes in an OpenAPI document.
+pub trait OpenApiExt {
+    // Adds `bearer_security_scheme()` under the name `BEARER_AUTH`, and `bearer_roles_security_scheme()` under
+    // the name `BEARER_ROLES`.
+    fn bearer_auth(self) -> Self;
+}
+
+impl OpenApiExt for OpenApi {
+    fn bearer_auth(self) -> Self {
+        self.add_security_scheme(BEARER_AUTH, bearer_security_scheme())
+            .add_security_scheme(BEARER_ROLES, bearer_roles_security_scheme())
+    }
+}
+
+// JwtRouterExt protects a router with a `JwtAuth` hoop and documents it on the router's endpoints.
+pub trait JwtRouterExt {
+    fn jwt_auth<C>(self, auth: JwtAuth<C>) -> Self
+    where
+        C: DeserializeOwned + AsRef<RegisteredClaims> + Send + Sync + 'static;
+}
+
+impl JwtRouterExt for Router {
+    fn jwt_auth<C>(self, auth: JwtAuth<C>) -> Self
+    where
+        C: DeserializeOwned + AsRef<RegisteredClaims> + Send + Sync + 'static,
+    {
+        self.oapi_security(auth.security_requirement()).hoop(auth)
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use salvo::{
+        handler,
+        http::header::COOKIE,
+        oapi::endpoint,
+        test::{ResponseExt as _, TestClient},
+        Service,
+    };
+
+    use super::*;
+    use crate::{
+        integrations::fixtures::{authenticator, token},
+        jws::CachedJwks,
+        Claims,
+    };
+
+    #[handler]
+    async fn subject(depot: &mut Depot) -> String {
+        depot.get_typed::<Claims>().unwrap().registered.subject.clone()
+    }
+
+    fn service(authenticator: Authenticator) -> Service {
+        Service::new(Router::new().jwt_auth(JwtAuth::<Claims>::new(authenticator)).get(subject))
+    }
+
+    #[tokio::test]
+    async fn injects_claims_into_the_depot() {
+        let service = service(authenticator());
+
+        let request = TestClient::get("http://127.0.0.1:5800/").add_header(AUTHORIZATION, format!("Bearer {}", token("joe", "")), true);
+        let mut response = request.send(&service).await;
+        assert_eq!(response.status_code, Some(StatusCode::OK));
+        assert_eq!(response.take_string().await.unwrap(), "alice");
+
+        let request = TestClient::get("http://127.0.0.1:5800/").add_header(COOKIE, format!("session={}", token("joe", "")), true);
+        let mut response = request.send(&service).await;
+        assert_eq!(response.take_string().await.unwrap(), "alice");
+
+        let request = TestClient::get(format!("http://127.0.0.1:5800/?access_token={}", token("joe", "")));
+        let mut response = request.send(&service).await;
+        assert_eq!(response.take_string().await.unwrap(), "alice");
+    }
+
+    #[tokio::test]
+    async fn rejects_unauthenticated_requests() {
+        let service = service(authenticator().with_required_scopes(["orders:read"]));
+
+        let response = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        assert_eq!(response.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(response.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer");
+
+        let request =
+            TestClient::get("http://127.0.0.1:5800/").add_header(AUTHORIZATION, format!("Bearer {}", token("mallory", "orders:read")), true);
+        let response = request.send(&service).await;
+        assert_eq!(response.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(
+            response.headers().get(WWW_AUTHENTICATE).unwrap(),
+            r#"Bearer error="invalid_token", error_description="invalid_issuer""#
+        );
+
+        let request = TestClient::get("http://127.0.0.1:5800/").add_header(AUTHORIZATION, format!("Bearer {}", token("joe", "orders:write")), true);
+        let response = request.send(&service).await;
+        assert_eq!(response.status_code, Some(StatusCode::FORBIDDEN));
+        assert_eq!(
+            response.headers().get(WWW_AUTHENTICATE).unwrap(),
+            r#"Bearer error="insufficient_scope", scope="orders:read""#
+        );
+    }
+
+    #[endpoint]
+    async fn orders(depot: &mut Depot) -> String {
+        depot.get_typed::<Claims>().unwrap().registered.subject.clone()
+    }
+
+    #[test]
+    fn documents_the_security_requirement() {
+        let auth = JwtAuth::<Claims>::new(authenticator().with_required_scopes(["orders:read"]).with_required_roles(["clerk"]));
+        let router = Router::new().push(Router::with_path("orders").jwt_auth(auth).get(orders));
+        let doc = OpenApi::new("orders", "1.0").bearer_auth().merge_router(&router);
+        let doc = serde_json::to_value(&doc).unwrap();
+
+        let schemes = &doc["components"]["securitySchemes"];
+        assert_eq!(
+            schemes[BEARER_AUTH],
+            serde_json::json!({ "type": "http", "scheme": "bearer", "bearerFormat": "JWT" })
+        );
+        assert_eq!(schemes[BEARER_ROLES]["scheme"], "bearer");
+        assert_eq!(
+            doc["paths"]["/orders"]["get"]["security"],
+            serde_json::json!([{ "bearerAuth": ["orders:read"], "bearerRoles": ["clerk"] }])
+        );
+
+        let auth = JwtAuth::<Claims>::new(authenticator());
+        let requirement = serde_json::to_value(auth.security_requirement()).unwrap();
+        assert_eq!(requirement, serde_json::json!({ "bearerAuth": [] }));
+    }
+
+    #[tokio::test]
+    async fn answers_503_without_a_key_set() {
+        let keys = CachedJwks::from_fetch(|| Err("connection refused".into()));
+        let service = service(Authenticator::new(keys));
+
+        let request = TestClient::get("http://127.0.0.1:5800/").add_header(AUTHORIZATION, format!("Bearer {}", token("joe", "")), true);
+        let response = request.send(&service).await;
+        assert_eq!(response.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(response.headers().get(WWW_AUTHENTICATE).is_none());
+    }
+}
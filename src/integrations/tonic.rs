@@ -0,0 +1,154 @@
+//! tonic support: the `JwtInterceptor` verifies the token in the `authorization` metadata of each gRPC request
+//! and inserts its claims into the request extensions.
+//!
+//! ```ignore
+//! let service = OrdersServer::with_interceptor(orders, JwtInterceptor::<Claims>::new(auth));
+//!
+//! async fn list(&self, request: Request<ListOrders>) -> Result<Response<Orders>, Status> {
+//!     let claims = request.extensions().get::<Claims>().unwrap();
+//!     ...
+//! }
+//! ```
+
+use std::{fmt, marker::PhantomData};
+
+use serde::de::DeserializeOwned;
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    service::Interceptor,
+    Code, Request, Status,
+};
+
+use super::{Authenticator, Rejection, TokenSource};
+use crate::{RegisteredClaims, ValidationError};
+
+// JwtInterceptor is a tonic interceptor that verifies the token of each request with an `Authenticator`.
+//
+// The token is read from the `authorization` metadata with the `Bearer` scheme, so only `TokenSource::Bearer`
+// is looked up. On success the claims, of type `C`, are inserted into the request extensions. Otherwise the
+// request fails with the `Status` returned by `status`.
+pub struct JwtInterceptor<C> {
+    authenticator: Authenticator,
+    claims: PhantomData<fn() -> C>,
+}
+
+impl<C> JwtInterceptor<C> {
+    pub fn new(authenticator: Authenticator) -> Self {
+        JwtInterceptor {
+            authenticator,
+            claims: PhantomData,
+        }
+    }
+
+    pub fn authenticator(&self) -> &Authenticator {
+        &self.authenticator
+    }
+}
+
+impl<C> Clone for JwtInterceptor<C> {
+    fn clone(&self) -> Self {
+        JwtInterceptor::new(self.authenticator.clone())
+    }
+}
+
+impl<C> fmt::Debug for JwtInterceptor<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtInterceptor").field("authenticator", &self.authenticator).finish()
+    }
+}
+
+impl<C> Interceptor for JwtInterceptor<C>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims> + Clone + Send + Sync + 'static,
+{
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let result = self.authenticator.authenticate::<C>(|source| match source {
+            TokenSource::Bearer => request.metadata().get("authorization")?.to_str().ok().map(str::to_string),
+            TokenSource::Cookie(_) | TokenSource::Query(_) => None,
+        });
+        let token = result.map_err(|err| status(&err))?;
+        request.extensions_mut().insert(token.claims);
+        Ok(request)
+    }
+}
+
+// Maps a failed authentication to a gRPC status: `PermissionDenied` if a required scope or role is missing,
+// `Unavailable` if the key set cannot be loaded, and `Unauthenticated` otherwise. The message describes the
+// error, the `error-reason` metadata carries its `ValidationError::reason`, and the `www-authenticate` metadata
+// carries the same Bearer challenge as an HTTP response would.
+// See https://grpc.github.io/grpc/core/md_doc_statuscodes.html
+pub fn status(err: &ValidationError) -> Status {
+    let code = match err {
+        ValidationError::InsufficientScope(_) | ValidationError::MissingRole(_) => Code::PermissionDenied,
+        ValidationError::KeySetUnavailable(_) => Code::Unavailable,
+        _ => Code::Unauthenticated,
+    };
+    let mut metadata = MetadataMap::new();
+    metadata.insert("error-reason", MetadataValue::from_static(err.reason()));
+    if let Some(value) = Rejection::new(err)
+        .challenge
+        .and_then(|challenge| MetadataValue::try_from(challenge).ok())
+    {
+        metadata.insert("www-authenticate", value);
+    }
+    Status::with_metadata(code, err.to_string(), metadata)
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use super::*;
+    use crate::{
+        integrations::fixtures::{authenticator, token},
+        Claims,
+    };
+
+    fn interceptor() -> JwtInterceptor<Claims> {
+        JwtInterceptor::new(authenticator().with_required_scopes(["orders:read"]))
+    }
+
+    fn request(issuer: &str, scope: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {}", token(issuer, scope)).parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn inserts_claims_into_extensions() {
+        let request = interceptor().call(request("joe", "orders:read")).unwrap();
+        let claims = request.extensions().get::<Claims>().unwrap();
+        assert_eq!(claims.registered.subject, "alice");
+    }
+
+    #[test]
+    fn maps_errors_to_statuses() {
+        let status = interceptor().call(Request::new(())).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(status.message(), "request carries no token");
+        assert_eq!(status.metadata().get("error-reason").unwrap(), "missing_token");
+        assert_eq!(status.metadata().get("www-authenticate").unwrap(), "Bearer");
+
+        let status = interceptor().call(request("mallory", "orders:read")).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(status.metadata().get("error-reason").unwrap(), "invalid_issuer");
+        assert_eq!(
+            status.metadata().get("www-authenticate").unwrap(),
+            r#"Bearer error="invalid_token", error_description="invalid_issuer""#
+        );
+
+        let status = interceptor().call(request("joe", "orders:write")).unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(status.message(), "token does not grant `orders:read`");
+        assert_eq!(status.metadata().get("error-reason").unwrap(), "insufficient_scope");
+
+        let status = super::status(&ValidationError::MissingRole("clerk".to_string()));
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(status.metadata().get("error-reason").unwrap(), "missing_role");
+
+        let status = super::status(&ValidationError::KeySetUnavailable("timed out".to_string()));
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.metadata().get("error-reason").unwrap(), "key_set_unavailable");
+        assert!(status.metadata().get("www-authenticate").is_none());
+    }
+}
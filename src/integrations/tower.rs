@@ -0,0 +1,286 @@
+//! tower support: `JwtLayer` verifies the token of every `http::Request` passing through a service stack and
+//! inserts its claims into the request extensions, so hyper, axum, tonic and other tower-based servers share
+//! one implementation.
+//!
+//! ```ignore
+//! let service = ServiceBuilder::new().layer(JwtLayer::<Claims>::new(auth)).service(inner);
+//!
+//! // in axum, the claims can then be extracted with `Extension<Claims>`.
+//! let app = Router::new().route("/", get(handler)).layer(JwtLayer::<Claims>::new(auth));
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use http::{
+    header::{HeaderValue, WWW_AUTHENTICATE},
+    Request, Response, StatusCode,
+};
+use pin_project_lite::pin_project;
+use serde::de::DeserializeOwned;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::{lookup_http, Authenticator, Rejection};
+use crate::{RegisteredClaims, ValidationError};
+
+// Reject builds the response sent back when a request fails authentication.
+//
+// It is implemented by `DefaultRejection` and by every `Fn(ValidationError) -> Response<B>`.
+pub trait Reject<B> {
+    fn reject(&self, err: ValidationError) -> Response<B>;
+}
+
+impl<B, F> Reject<B> for F
+where
+    F: Fn(ValidationError) -> Response<B>,
+{
+    fn reject(&self, err: ValidationError) -> Response<B> {
+        self(err)
+    }
+}
+
+// DefaultRejection answers as `integrations::Rejection` describes, with an empty body.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRejection;
+
+impl<B: Default> Reject<B> for DefaultRejection {
+    fn reject(&self, err: ValidationError) -> Response<B> {
+        let rejection = Rejection::new(&err);
+        let mut response = Response::new(B::default());
+        *response.status_mut() = StatusCode::from_u16(rejection.status).unwrap_or(StatusCode::UNAUTHORIZED);
+        if let Some(value) = rejection.challenge.and_then(|challenge| HeaderValue::from_str(&challenge).ok()) {
+            response.headers_mut().insert(WWW_AUTHENTICATE, value);
+        }
+        response
+    }
+}
+
+// JwtLayer wraps services in a `JwtService`, which verifies the token of each request with an `Authenticator`.
+//
+// On success the claims, of type `C`, are inserted into the request extensions before it reaches the inner
+// service. Otherwise the inner service is not called, and the response is built by the rejection, by default
+// `DefaultRejection`.
+pub struct JwtLayer<C, R = DefaultRejection> {
+    authenticator: Authenticator,
+    rejection: R,
+    claims: PhantomData<fn() -> C>,
+}
+
+impl<C> JwtLayer<C> {
+    pub fn new(authenticator: Authenticator) -> Self {
+        JwtLayer {
+            authenticator,
+            rejection: DefaultRejection,
+            claims: PhantomData,
+        }
+    }
+}
+
+impl<C, R> JwtLayer<C, R> {
+    // Replaces how failed requests are answered, e.g. with a closure rendering the error as JSON.
+    pub fn with_rejection<T>(self, rejection: T) -> JwtLayer<C, T> {
+        JwtLayer {
+            authenticator: self.authenticator,
+            rejection,
+            claims: PhantomData,
+        }
+    }
+
+    pub fn authenticator(&self) -> &Authenticator {
+        &self.authenticator
+    }
+}
+
+impl<C, R: Clone> Clone for JwtLayer<C, R> {
+    fn clone(&self) -> Self {
+        JwtLayer {
+            authenticator: self.authenticator.clone(),
+            rejection: self.rejection.clone(),
+            claims: PhantomData,
+        }
+    }
+}
+
+impl<C, R> fmt::Debug for JwtLayer<C, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtLayer").field("authenticator", &self.authenticator).finish()
+    }
+}
+
+impl<S, C, R: Clone> Layer<S> for JwtLayer<C, R> {
+    type Service = JwtService<S, C, R>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtService {
+            inner,
+            authenticator: self.authenticator.clone(),
+            rejection: self.rejection.clone(),
+            claims: PhantomData,
+        }
+    }
+}
+
+// JwtService is the service `JwtLayer` wraps around an inner service.
+pub struct JwtService<S, C, R = DefaultRejection> {
+    inner: S,
+    authenticator: Authenticator,
+    rejection: R,
+    claims: PhantomData<fn() -> C>,
+}
+
+impl<S, C> JwtService<S, C> {
+    pub fn new(inner: S, authenticator: Authenticator) -> Self {
+        JwtLayer::new(authenticator).layer(inner)
+    }
+}
+
+impl<S, C, R> JwtService<S, C, R> {
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Clone, C, R: Clone> Clone for JwtService<S, C, R> {
+    fn clone(&self) -> Self {
+        JwtService {
+            inner: self.inner.clone(),
+            authenticator: self.authenticator.clone(),
+            rejection: self.rejection.clone(),
+            claims: PhantomData,
+        }
+    }
+}
+
+impl<S: fmt::Debug, C, R> fmt::Debug for JwtService<S, C, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtService")
+            .field("inner", &self.inner)
+            .field("authenticator", &self.authenticator)
+            .finish()
+    }
+}
+
+impl<S, C, R, ReqBody, ResBody> Service<Request<ReqBody>> for JwtService<S, C, R>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    C: DeserializeOwned + AsRef<RegisteredClaims> + Clone + Send + Sync + 'static,
+    R: Reject<ResBody>,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        match self
+            .authenticator
+            .authenticate::<C>(|source| lookup_http(source, req.headers(), req.uri()))
+        {
+            Ok(token) => {
+                req.extensions_mut().insert(token.claims);
+                ResponseFuture::Authorized {
+                    future: self.inner.call(req),
+                }
+            }
+            Err(err) => ResponseFuture::Rejected {
+                response: Some(self.rejection.reject(err)),
+            },
+        }
+    }
+}
+
+pin_project! {
+    // ResponseFuture is the future returned by `JwtService`: the inner service's response, or the rejection.
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        Authorized {
+            #[pin]
+            future: F,
+        },
+        Rejected {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Authorized { future } => future.poll(cx),
+            ResponseFutureProj::Rejected { response } => Poll::Ready(Ok(response.take().expect("ResponseFuture polled after completion"))),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, ServiceExt as _};
+
+    use super::*;
+    use crate::integrations::fixtures::{authenticator, token};
+
+    async fn subject(req: Request<()>) -> Result<Response<String>, Infallible> {
+        let claims = req.extensions().get::<RegisteredClaims>().unwrap();
+        Ok(Response::new(claims.subject.clone()))
+    }
+
+    #[tokio::test]
+    async fn inserts_claims_into_extensions() {
+        let service = JwtLayer::<RegisteredClaims>::new(authenticator()).layer(service_fn(subject));
+
+        let request = Request::builder().header("Authorization", format!("Bearer {}", token("joe", "")));
+        let response = service.clone().oneshot(request.body(()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "alice");
+
+        let request = Request::builder().uri(format!("/orders?access_token={}", token("joe", "")));
+        let response = service.clone().oneshot(request.body(()).unwrap()).await.unwrap();
+        assert_eq!(response.body(), "alice");
+
+        let response = service.clone().oneshot(Request::new(())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+        assert_eq!(response.body(), "");
+
+        let request = Request::builder().header("Authorization", format!("Bearer {}", token("mallory", "")));
+        let response = service.oneshot(request.body(()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            r#"Bearer error="invalid_token", error_description="invalid_issuer""#
+        );
+    }
+
+    #[tokio::test]
+    async fn uses_custom_rejections() {
+        let layer = JwtLayer::<RegisteredClaims>::new(authenticator()).with_rejection(|err: ValidationError| {
+            let mut response = Response::new(format!(r#"{{"error":"{}"}}"#, err.reason()));
+            *response.status_mut() = StatusCode::UNAUTHORIZED;
+            response
+        });
+        let response = layer.layer(service_fn(subject)).oneshot(Request::new(())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!response.headers().contains_key(WWW_AUTHENTICATE));
+        assert_eq!(response.body(), r#"{"error":"missing_token"}"#);
+    }
+}
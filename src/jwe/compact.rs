@@ -0,0 +1,370 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand_core::{OsRng, RngCore};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{content, DecryptionKey, EncryptionKey, Header};
+use crate::{Clock, RegisteredClaims, SystemClock, Validation, ValidationError};
+
+// TokenData is a decrypted and validated token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenData<C> {
+    pub header: Header,
+    pub claims: C,
+}
+
+// Encrypts `claims` into a JWE Compact Serialization for `key`, using the algorithms named by `header`.
+// Parameters the key management algorithm needs, such as `epk`, are added to the emitted header.
+pub fn encrypt<C: Serialize>(header: &Header, claims: &C, key: &EncryptionKey) -> Result<String, ValidationError> {
+    let payload = serde_json::to_vec(claims).map_err(|e| ValidationError::MalformedToken(e.to_string()))?;
+    encrypt_payload(header, &payload, key)
+}
+
+// Encrypts an arbitrary payload, such as a signed JWT for a Nested JWT.
+pub(crate) fn encrypt_payload(header: &Header, payload: &[u8], key: &EncryptionKey) -> Result<String, ValidationError> {
+    if header.compression.is_some() {
+        return Err(ValidationError::MalformedToken("compressed tokens are not supported".to_string()));
+    }
+    let mut header = header.clone();
+    let random_cek = random_bytes(header.encryption.key_len());
+    let (cek, encrypted_key) = key.encrypt_cek(&mut header, random_cek)?;
+    let iv = random_bytes(header.encryption.iv_len());
+    seal(&header, payload, &cek, &encrypted_key, &iv)
+}
+
+// Assembles a token from an already determined Content Encryption Key, JWE Encrypted Key and Initialization Vector.
+fn seal(header: &Header, payload: &[u8], cek: &[u8], encrypted_key: &[u8], iv: &[u8]) -> Result<String, ValidationError> {
+    let header_json = serde_json::to_vec(header).map_err(|e| ValidationError::MalformedToken(e.to_string()))?;
+    let protected = URL_SAFE_NO_PAD.encode(header_json);
+    let (ciphertext, tag) = content::encrypt(header.encryption, cek, iv, protected.as_bytes(), payload)?;
+    Ok(format!(
+        "{protected}.{}.{}.{}.{}",
+        URL_SAFE_NO_PAD.encode(encrypted_key),
+        URL_SAFE_NO_PAD.encode(iv),
+        URL_SAFE_NO_PAD.encode(ciphertext),
+        URL_SAFE_NO_PAD.encode(tag)
+    ))
+}
+
+// Decrypts a JWE Compact Serialization with `key`, then validates its header and claims against `validation`.
+pub fn decrypt<C>(token: &str, key: &DecryptionKey, validation: &Validation) -> Result<TokenData<C>, ValidationError>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims>,
+{
+    decrypt_at(token, key, validation, &SystemClock)
+}
+
+// Like `decrypt`, but reads the current time from `clock`.
+pub fn decrypt_at<C>(token: &str, key: &DecryptionKey, validation: &Validation, clock: &dyn Clock) -> Result<TokenData<C>, ValidationError>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims>,
+{
+    let (header, payload) = decrypt_payload(token, key, validation)?;
+    let claims: C = serde_json::from_slice(&payload).map_err(|e| ValidationError::MalformedToken(e.to_string()))?;
+    validation.validate_at(claims.as_ref(), clock)?;
+    Ok(TokenData { header, claims })
+}
+
+// Decrypts a token to its raw payload. The header is checked against `validation` and the algorithms `key` allows
+// before any key material is used.
+pub(crate) fn decrypt_payload(token: &str, key: &DecryptionKey, validation: &Validation) -> Result<(Header, Vec<u8>), ValidationError> {
+    let segments: Vec<&str> = token.split('.').collect();
+    let [protected, encrypted_key, iv, ciphertext, tag] = segments[..] else {
+        return Err(ValidationError::MalformedToken("expected five segments".to_string()));
+    };
+
+    let header: Header = serde_json::from_slice(&decode_base64(protected)?).map_err(|e| ValidationError::MalformedToken(e.to_string()))?;
+    validation.validate_jwe_header(&header)?;
+    if header.compression.is_some() {
+        return Err(ValidationError::MalformedToken("compressed tokens are not supported".to_string()));
+    }
+    key.check_algorithms(&header)?;
+
+    let (encrypted_key, iv, ciphertext, tag) = (
+        decode_base64(encrypted_key)?,
+        decode_base64(iv)?,
+        decode_base64(ciphertext)?,
+        decode_base64(tag)?,
+    );
+    // A Content Encryption Key that fails to decrypt is replaced with a random one, so the failure surfaces at the
+    // same point as a forged ciphertext. See https://datatracker.ietf.org/doc/html/rfc7516#section-11.5
+    let cek = match key.decrypt_cek(&header, &encrypted_key) {
+        Err(ValidationError::DecryptionFailed) => random_bytes(header.encryption.key_len()),
+        result => result?,
+    };
+    let payload = content::decrypt(header.encryption, &cek, &iv, protected.as_bytes(), &ciphertext, &tag)?;
+    Ok((header, payload))
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn decode_base64(segment: &str) -> Result<Vec<u8>, ValidationError> {
+    URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|e| ValidationError::MalformedToken(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone as _, Utc};
+
+    use super::*;
+    use crate::{
+        jwe::{Encryption, KeyAlgorithm},
+        Claims,
+    };
+
+    // https://datatracker.ietf.org/doc/html/rfc7516#appendix-A.1
+    const RFC7516_A1_TOKEN: &str = "eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkEyNTZHQ00ifQ.OKOawDo13gRp2ojaHV7LFpZcgV7T6DVZKTyKOMTYUmKoTCVJRgckCL9kiMT03JGeipsEdY3mx_etLbbWSrFr05kLzcSr4qKAq7YN7e9jwQRb23nfa6c9d-StnImGyFDbSv04uVuxIp5Zms1gNxKKK2Da14B8S4rzVRltdYwam_lDp5XnZAYpQdb76FdIKLaVmqgfwX7XWRxv2322i-vDxRfqNzo_tETKzpVLzfiwQyeyPGLBIO56YJ7eObdv0je81860ppamavo35UgoRdbYaBcoh9QcfylQr66oc6vFWXRcZ_ZT2LawVCWTIy3brGPi6UklfCpIMfIjf7iGdXKHzg.48V1_ALb6US04U3b.5eym8TW_c8SuK0ltJ3rpYIzOeDQz7TALvtu6UG9oMo4vpzs9tX_EFShS8iB7j6jiSdiwkIr3ajwQzaBtQD_A.XFBoMYUZodetZdvTiFvSkQ";
+
+    // https://datatracker.ietf.org/doc/html/rfc7516#appendix-A.3
+    const RFC7516_A3_TOKEN: &str = "eyJhbGciOiJBMTI4S1ciLCJlbmMiOiJBMTI4Q0JDLUhTMjU2In0.6KB707dM9YTIgHtLvtgWQ8mKwboJW3of9locizkDTHzBC2IlrT1oOQ.AxY8DCtDaGlsbGljb3RoZQ.KDlTtXchhZTGufMYmOYGS4HffxPSUrfmqCHXaI9wOGY.U0m_YmjN04DJvceFICbCVQ";
+    const RFC7516_A3_KEY: &str = "GawgguFyGrWKav7AX4VKUg";
+    const RFC7516_A3_CEK: [u8; 32] = [
+        4, 211, 31, 197, 84, 157, 252, 254, 11, 100, 157, 250, 63, 170, 106, 206, 107, 124, 212, 45, 111, 107, 9, 219, 200, 177, 0, 240, 143, 156,
+        44, 207,
+    ];
+
+    // https://datatracker.ietf.org/doc/html/rfc7520#section-5.6 and https://datatracker.ietf.org/doc/html/rfc7520#section-5.8
+    const RFC7520_PLAINTEXT: &str = "You can trust us to stick with you through thick and thin\u{2013}to the bitter end. And you can trust us to keep any secret of yours\u{2013}closer than you keep it yourself. But you cannot trust us to let you face trouble alone, and go off without a word. We are your friends, Frodo.";
+    const RFC7520_5_6_TOKEN: &str = "eyJhbGciOiJkaXIiLCJraWQiOiI3N2M3ZTJiOC02ZTEzLTQ1Y2YtODY3Mi02MTdiNWI0NTI0M2EiLCJlbmMiOiJBMTI4R0NNIn0..refa467QzzKx6QAB.JW_i_f52hww_ELQPGaYyeAB6HYGcR559l9TYnSovc23XJoBcW29rHP8yZOZG7YhLpT1bjFuvZPjQS-m0IFtVcXkZXdH_lr_FrdYt9HRUYkshtrMmIUAyGmUnd9zMDB2n0cRDIHAzFVeJUDxkUwVAE7_YGRPdcqMyiBoCO-FBdE-Nceb4h3-FtBP-c_BIwCPTjb9o0SbdcdREEMJMyZBH8ySWMVi1gPD9yxi-aQpGbSv_F9N4IZAxscj5g-NJsUPbjk29-s7LJAGb15wEBtXphVCgyy53CoIKLHHeJHXex45Uz9aKZSRSInZI-wjsY0yu3cT4_aQ3i1o-tiE-F8Ios61EKgyIQ4CWao8PFMj8TTnp.vbb32Xvllea2OtmHAdccRQ";
+    const RFC7520_5_6_KEY: &str = "XctOhJAkA-pD9Lh7ZgW_2A";
+    const RFC7520_5_8_TOKEN: &str = "eyJhbGciOiJBMTI4S1ciLCJraWQiOiI4MWIyMDk2NS04MzMyLTQzZDktYTQ2OC04MjE2MGFkOTFhYzgiLCJlbmMiOiJBMTI4R0NNIn0.CBI6oDw8MydIx1IBntf_lQcw2MmJKIQx.Qx0pmsDa8KnJc9Jo.AwliP-KmWgsZ37BvzCefNen6VTbRK3QMA4TkvRkH0tP1bTdhtFJgJxeVmJkLD61A1hnWGetdg11c9ADsnWgL56NyxwSYjU1ZEHcGkd3EkU0vjHi9gTlb90qSYFfeF0LwkcTtjbYKCsiNJQkcIp1yeM03OmuiYSoYJVSpf7ej6zaYcMv3WwdxDFl8REwOhNImk2Xld2JXq6BR53TSFkyT7PwVLuq-1GwtGHlQeg7gDT6xW0JqHDPn_H-puQsmthc9Zg0ojmJfqqFvETUxLAF-KjcBTS5dNy6egwkYtOt8EIHK-oEsKYtZRaa8Z7MOZ7UGxGIMvEmxrGCPeJa14slv2-gaqK0kEThkaSqdYw0FkQZF.ER7MWJZ1FBI_NKvn7Zb1Lw";
+    const RFC7520_5_8_KEY: &str = "GZy6sIZ6wl9NJOKB-jnmVQ";
+    // https://datatracker.ietf.org/doc/html/rfc7520#section-5.4
+    const RFC7520_5_4_TOKEN: &str = "eyJhbGciOiJFQ0RILUVTK0ExMjhLVyIsImtpZCI6InBlcmVncmluLnRvb2tAdHVja2Jvcm91Z2guZXhhbXBsZSIsImVwayI6eyJrdHkiOiJFQyIsImNydiI6IlAtMzg0IiwieCI6InVCbzRrSFB3Nmtiang1bDB4b3dyZF9vWXpCbWF6LUdLRlp1NHhBRkZrYllpV2d1dEVLNml1RURzUTZ3TmROZzMiLCJ5Ijoic3AzcDVTR2haVkMyZmFYdW1JLWU5SlUyTW84S3BvWXJGRHI1eVBOVnRXNFBnRXdaT3lRVEEtSmRhWTh0YjdFMCJ9LCJlbmMiOiJBMTI4R0NNIn0.0DJjBXri_kBcC46IkU5_Jk9BqaQeHdv2.mH-G2zVqgztUtnW_.tkZuOO9h95OgHJmkkrfLBisku8rGf6nzVxhRM3sVOhXgz5NJ76oID7lpnAi_cPWJRCjSpAaUZ5dOR3Spy7QuEkmKx8-3RCMhSYMzsXaEwDdXta9Mn5B7cCBoJKB0IgEnj_qfo1hIi-uEkUpOZ8aLTZGHfpl05jMwbKkTe2yK3mjF6SBAsgicQDVCkcY9BLluzx1RmC3ORXaM0JaHPB93YcdSDGgpgBWMVrNU1ErkjcMqMoT_wtCex3w03XdLkjXIuEr2hWgeP-nkUZTPU9EoGSPj6fAS-bSz87RCPrxZdj_iVyC6QWcqAu07WNhjzJEPc4jVntRJ6K53NgPQ5p99l3Z408OUqj4ioYezbS6vTPlQ.WuGzxmcreYjpHGJoa17EBg";
+
+    fn secret(value: &str) -> Vec<u8> {
+        URL_SAFE_NO_PAD.decode(value).unwrap()
+    }
+
+    fn segment(token: &str, index: usize) -> Vec<u8> {
+        decode_base64(token.split('.').nth(index).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn matches_rfc7516_aes_key_wrap_example() {
+        let key = DecryptionKey::from_secret(&secret(RFC7516_A3_KEY));
+        let (header, payload) = decrypt_payload(RFC7516_A3_TOKEN, &key, &Validation::new()).unwrap();
+        assert_eq!(header.algorithm, KeyAlgorithm::A128KW);
+        assert_eq!(header.encryption, Encryption::A128CbcHs256);
+        assert_eq!(payload, b"Live long and prosper.");
+
+        let mut header = Header::new(KeyAlgorithm::A128KW, Encryption::A128CbcHs256);
+        header.token_type = None;
+        let token = seal(
+            &header,
+            b"Live long and prosper.",
+            &RFC7516_A3_CEK,
+            &segment(RFC7516_A3_TOKEN, 1),
+            &segment(RFC7516_A3_TOKEN, 2),
+        )
+        .unwrap();
+        assert_eq!(token, RFC7516_A3_TOKEN);
+    }
+
+    #[test]
+    fn matches_rfc7516_rsa_oaep_example() {
+        let key = DecryptionKey::from_rsa_pem(include_str!("../../testdata/rfc7516_a1_private.pem")).unwrap();
+        let (header, payload) = decrypt_payload(RFC7516_A1_TOKEN, &key, &Validation::new()).unwrap();
+        assert_eq!(header.algorithm, KeyAlgorithm::RsaOaep);
+        assert_eq!(header.encryption, Encryption::A256GCM);
+        assert_eq!(payload, b"The true sign of intelligence is not knowledge but imagination.");
+    }
+
+    #[test]
+    fn matches_rfc7520_examples() {
+        let key = DecryptionKey::from_secret(&secret(RFC7520_5_6_KEY));
+        let (header, payload) = decrypt_payload(RFC7520_5_6_TOKEN, &key, &Validation::new()).unwrap();
+        assert_eq!(header.algorithm, KeyAlgorithm::Direct);
+        assert_eq!(header.key_id.as_deref(), Some("77c7e2b8-6e13-45cf-8672-617b5b45243a"));
+        assert_eq!(payload, RFC7520_PLAINTEXT.as_bytes());
+
+        let key = DecryptionKey::from_secret(&secret(RFC7520_5_8_KEY));
+        let (header, payload) = decrypt_payload(RFC7520_5_8_TOKEN, &key, &Validation::new()).unwrap();
+        assert_eq!(header.algorithm, KeyAlgorithm::A128KW);
+        assert_eq!(header.encryption, Encryption::A128GCM);
+        assert_eq!(payload, RFC7520_PLAINTEXT.as_bytes());
+
+        let key = DecryptionKey::from_ec_pem(include_str!("../../testdata/rfc7520_5_4_private.pem")).unwrap();
+        let (header, payload) = decrypt_payload(RFC7520_5_4_TOKEN, &key, &Validation::new()).unwrap();
+        assert_eq!(header.algorithm, KeyAlgorithm::EcdhEsA128KW);
+        assert_eq!(header.key_id.as_deref(), Some("peregrin.took@tuckborough.example"));
+        assert_eq!(payload, RFC7520_PLAINTEXT.as_bytes());
+
+        let err = decrypt_payload(
+            RFC7520_5_8_TOKEN,
+            &DecryptionKey::from_secret(&secret(RFC7520_5_6_KEY)),
+            &Validation::new(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ValidationError::DecryptionFailed);
+    }
+
+    #[test]
+    fn round_trips_every_algorithm() {
+        let claims = Claims {
+            registered: RegisteredClaims {
+                subject: "alice".to_string(),
+                expires_at: Utc.timestamp_opt(Utc::now().timestamp() + 300, 0).single(),
+                ..Default::default()
+            },
+            custom: (),
+            extra: serde_json::from_str(r#"{"scope":"read"}"#).unwrap(),
+        };
+        let rsa = (
+            EncryptionKey::from_rsa_pem(include_str!("../../testdata/rfc7515_a2_public.pem")).unwrap(),
+            DecryptionKey::from_rsa_pem(include_str!("../../testdata/rfc7515_a2_private.pem")).unwrap(),
+        );
+        let ec = [
+            (
+                include_str!("../../testdata/p256_public.pem"),
+                include_str!("../../testdata/p256_private.pem"),
+            ),
+            (
+                include_str!("../../testdata/p384_public.pem"),
+                include_str!("../../testdata/p384_private_sec1.pem"),
+            ),
+            (
+                include_str!("../../testdata/p521_public.pem"),
+                include_str!("../../testdata/p521_private.pem"),
+            ),
+        ]
+        .map(|(public, private)| (EncryptionKey::from_ec_pem(public).unwrap(), DecryptionKey::from_ec_pem(private).unwrap()));
+        let encryptions = [
+            Encryption::A128CbcHs256,
+            Encryption::A192CbcHs384,
+            Encryption::A256CbcHs512,
+            Encryption::A128GCM,
+            Encryption::A192GCM,
+            Encryption::A256GCM,
+        ];
+        let validation = Validation::new().with_subject("alice").with_token_type("JWT");
+
+        for enc in encryptions {
+            let mut cases = vec![
+                (KeyAlgorithm::RsaOaep, rsa.clone()),
+                (KeyAlgorithm::RsaOaep256, rsa.clone()),
+                (
+                    KeyAlgorithm::A128KW,
+                    (EncryptionKey::from_secret(&[1; 16]), DecryptionKey::from_secret(&[1; 16])),
+                ),
+                (
+                    KeyAlgorithm::A192KW,
+                    (EncryptionKey::from_secret(&[1; 24]), DecryptionKey::from_secret(&[1; 24])),
+                ),
+                (
+                    KeyAlgorithm::A256KW,
+                    (EncryptionKey::from_secret(&[1; 32]), DecryptionKey::from_secret(&[1; 32])),
+                ),
+                (
+                    KeyAlgorithm::Direct,
+                    (
+                        EncryptionKey::from_secret(&vec![2; enc.key_len()]),
+                        DecryptionKey::from_secret(&vec![2; enc.key_len()]),
+                    ),
+                ),
+            ];
+            for alg in [
+                KeyAlgorithm::EcdhEs,
+                KeyAlgorithm::EcdhEsA128KW,
+                KeyAlgorithm::EcdhEsA192KW,
+                KeyAlgorithm::EcdhEsA256KW,
+            ] {
+                cases.extend(ec.iter().map(|keys| (alg, keys.clone())));
+            }
+
+            for (alg, (encryption_key, decryption_key)) in cases {
+                let mut header = Header::new(alg, enc);
+                header.party_u_info = Some(URL_SAFE_NO_PAD.encode("alice"));
+                let token = encrypt(&header, &claims, &encryption_key).unwrap();
+                let decrypted: TokenData<Claims> = decrypt(&token, &decryption_key, &validation).unwrap();
+                assert_eq!(decrypted.claims, claims, "{alg} {enc}");
+                assert_eq!(decrypted.header.algorithm, alg);
+                assert_eq!(decrypted.header.ephemeral_key.is_some(), alg.name().starts_with("ECDH-ES"));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_and_malformed_tokens() {
+        let key = DecryptionKey::from_secret(&secret(RFC7516_A3_KEY));
+        let validation = Validation::new();
+        let segments: Vec<&str> = RFC7516_A3_TOKEN.split('.').collect();
+
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"A128KW","enc":"A128CBC-HS256","kid":"x"}"#);
+        let mut forged_key = segment(RFC7516_A3_TOKEN, 1);
+        forged_key[0] ^= 1;
+        let forged_key = URL_SAFE_NO_PAD.encode(forged_key);
+        let mut forged_ciphertext = segment(RFC7516_A3_TOKEN, 3);
+        forged_ciphertext[0] ^= 1;
+        let forged_ciphertext = URL_SAFE_NO_PAD.encode(forged_ciphertext);
+        for (index, value) in [
+            (0, header.as_str()),
+            (1, forged_key.as_str()),
+            (3, forged_ciphertext.as_str()),
+            (4, segments[1]),
+        ] {
+            let mut forged = segments.clone();
+            forged[index] = value;
+            let err = decrypt_payload(&forged.join("."), &key, &validation).unwrap_err();
+            assert_eq!(err, ValidationError::DecryptionFailed, "{index}");
+        }
+
+        let err = decrypt_payload(RFC7516_A3_TOKEN, &DecryptionKey::from_secret(&[0; 16]), &validation).unwrap_err();
+        assert_eq!(err, ValidationError::DecryptionFailed);
+        let key_for_a128kw = DecryptionKey::from_secret(&[0; 32]).with_algorithms([KeyAlgorithm::A128KW]);
+        let err = decrypt_payload(RFC7516_A3_TOKEN, &key_for_a128kw, &validation).unwrap_err();
+        assert_eq!(err.reason(), "invalid_key");
+
+        let zip = URL_SAFE_NO_PAD.encode(r#"{"alg":"A128KW","enc":"A128CBC-HS256","zip":"DEF"}"#);
+        let unknown = URL_SAFE_NO_PAD.encode(r#"{"alg":"PBES2-HS256+A128KW","enc":"A128CBC-HS256"}"#);
+        for header in [zip.as_str(), unknown.as_str(), "!!"] {
+            let token = [header, segments[1], segments[2], segments[3], segments[4]].join(".");
+            let err = decrypt_payload(&token, &key, &validation).unwrap_err();
+            assert_eq!(err.reason(), "malformed_token", "{header}");
+        }
+        for token in ["", "a.b.c", "a.b.c.d.e.f"] {
+            let err = decrypt_payload(token, &key, &validation).unwrap_err();
+            assert_eq!(err.reason(), "malformed_token", "{token}");
+        }
+
+        let err = decrypt_payload(RFC7516_A3_TOKEN, &key, &validation.with_token_type("JWT")).unwrap_err();
+        assert_eq!(err, ValidationError::InvalidTokenType(String::new()));
+    }
+
+    #[test]
+    fn restricts_keys_to_their_algorithms() {
+        let secret = DecryptionKey::from_secret(&secret(RFC7516_A3_KEY));
+        assert_eq!(secret.algorithms(), [KeyAlgorithm::Direct, KeyAlgorithm::A128KW]);
+        let err = decrypt_payload(RFC7516_A3_TOKEN, &DecryptionKey::from_secret(&[0; 32]), &Validation::new()).unwrap_err();
+        assert_eq!(err, ValidationError::EncryptionNotAllowed("A128KW".to_string()));
+
+        let key = secret.clone().with_algorithms([KeyAlgorithm::Direct]);
+        let err = decrypt_payload(RFC7516_A3_TOKEN, &key, &Validation::new()).unwrap_err();
+        assert_eq!(err, ValidationError::EncryptionNotAllowed("A128KW".to_string()));
+
+        let key = secret.clone().with_encryptions([Encryption::A256GCM]);
+        let err = decrypt_payload(RFC7516_A3_TOKEN, &key, &Validation::new()).unwrap_err();
+        assert_eq!(err, ValidationError::EncryptionNotAllowed("A128CBC-HS256".to_string()));
+        assert!(decrypt_payload(RFC7516_A3_TOKEN, &key.with_encryptions([Encryption::A128CbcHs256]), &Validation::new()).is_ok());
+
+        let rsa = DecryptionKey::from_rsa_pem(include_str!("../../testdata/rfc7516_a1_private.pem")).unwrap();
+        let err = decrypt_payload(RFC7516_A1_TOKEN, &rsa.with_algorithms([KeyAlgorithm::RsaOaep256]), &Validation::new()).unwrap_err();
+        assert_eq!(err.reason(), "encryption_not_allowed");
+    }
+
+    #[test]
+    fn rejects_keys_for_other_algorithms() {
+        let rsa = EncryptionKey::from_rsa_pem(include_str!("../../testdata/rfc7515_a2_public.pem")).unwrap();
+        let header = Header::new(KeyAlgorithm::A128KW, Encryption::A128GCM);
+        assert_eq!(encrypt(&header, &RegisteredClaims::default(), &rsa).unwrap_err().reason(), "invalid_key");
+
+        let header = Header::new(KeyAlgorithm::Direct, Encryption::A256GCM);
+        let key = EncryptionKey::from_secret(&[0; 16]);
+        assert_eq!(encrypt(&header, &RegisteredClaims::default(), &key).unwrap_err().reason(), "invalid_key");
+    }
+}
@@ -0,0 +1,164 @@
+use aes::cipher::{block_padding::Pkcs7, BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyInit, KeyIvInit};
+use aes_gcm::{
+    aead::{consts::U12, generic_array::GenericArray, Aead, Payload},
+    AesGcm,
+};
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+use subtle::ConstantTimeEq;
+
+use super::Encryption;
+use crate::ValidationError;
+
+// Encrypts `plaintext` with the Content Encryption Key, returning the JWE Ciphertext and Authentication Tag.
+// The caller is responsible for the sizes of `cek` and `iv`, see `Encryption::key_len` and `Encryption::iv_len`.
+pub(crate) fn encrypt(enc: Encryption, cek: &[u8], iv: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ValidationError> {
+    match enc {
+        Encryption::A128CbcHs256 => cbc_hmac_encrypt::<aes::Aes128, Hmac<Sha256>>(cek, iv, aad, plaintext),
+        Encryption::A192CbcHs384 => cbc_hmac_encrypt::<aes::Aes192, Hmac<Sha384>>(cek, iv, aad, plaintext),
+        Encryption::A256CbcHs512 => cbc_hmac_encrypt::<aes::Aes256, Hmac<Sha512>>(cek, iv, aad, plaintext),
+        Encryption::A128GCM => gcm_encrypt::<aes::Aes128>(cek, iv, aad, plaintext),
+        Encryption::A192GCM => gcm_encrypt::<aes::Aes192>(cek, iv, aad, plaintext),
+        Encryption::A256GCM => gcm_encrypt::<aes::Aes256>(cek, iv, aad, plaintext),
+    }
+}
+
+// Authenticates and decrypts the JWE Ciphertext. Every failure is reported as `DecryptionFailed`, without detail.
+pub(crate) fn decrypt(enc: Encryption, cek: &[u8], iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    if cek.len() != enc.key_len() || iv.len() != enc.iv_len() {
+        return Err(ValidationError::DecryptionFailed);
+    }
+    match enc {
+        Encryption::A128CbcHs256 => cbc_hmac_decrypt::<aes::Aes128, Hmac<Sha256>>(cek, iv, aad, ciphertext, tag),
+        Encryption::A192CbcHs384 => cbc_hmac_decrypt::<aes::Aes192, Hmac<Sha384>>(cek, iv, aad, ciphertext, tag),
+        Encryption::A256CbcHs512 => cbc_hmac_decrypt::<aes::Aes256, Hmac<Sha512>>(cek, iv, aad, ciphertext, tag),
+        Encryption::A128GCM => gcm_decrypt::<aes::Aes128>(cek, iv, aad, ciphertext, tag),
+        Encryption::A192GCM => gcm_decrypt::<aes::Aes192>(cek, iv, aad, ciphertext, tag),
+        Encryption::A256GCM => gcm_decrypt::<aes::Aes256>(cek, iv, aad, ciphertext, tag),
+    }
+}
+
+// AES_CBC_HMAC_SHA2: the first half of the key authenticates, the second half encrypts, and the tag is the first
+// half of the MAC. See https://datatracker.ietf.org/doc/html/rfc7518#section-5.2.2
+fn cbc_hmac_encrypt<C, M>(cek: &[u8], iv: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ValidationError>
+where
+    C: BlockCipher + BlockEncryptMut + KeyInit,
+    M: Mac + KeyInit,
+{
+    let (mac_key, enc_key) = cek.split_at(cek.len() / 2);
+    let ciphertext = cbc::Encryptor::<C>::new_from_slices(enc_key, iv)
+        .map_err(|e| ValidationError::InvalidKey(e.to_string()))?
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+    let tag = cbc_hmac_tag::<M>(mac_key, iv, aad, &ciphertext);
+    Ok((ciphertext, tag))
+}
+
+fn cbc_hmac_decrypt<C, M>(cek: &[u8], iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, ValidationError>
+where
+    C: BlockCipher + BlockDecryptMut + KeyInit,
+    M: Mac + KeyInit,
+{
+    let (mac_key, enc_key) = cek.split_at(cek.len() / 2);
+    if cbc_hmac_tag::<M>(mac_key, iv, aad, ciphertext).ct_eq(tag).unwrap_u8() != 1 {
+        return Err(ValidationError::DecryptionFailed);
+    }
+    cbc::Decryptor::<C>::new_from_slices(enc_key, iv)
+        .map_err(|_| ValidationError::DecryptionFailed)?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| ValidationError::DecryptionFailed)
+}
+
+fn cbc_hmac_tag<M: Mac + KeyInit>(mac_key: &[u8], iv: &[u8], aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(mac_key).expect("HMAC accepts keys of any size");
+    mac.update(aad);
+    mac.update(iv);
+    mac.update(ciphertext);
+    mac.update(&(aad.len() as u64 * 8).to_be_bytes());
+    let mut tag = mac.finalize().into_bytes().to_vec();
+    tag.truncate(mac_key.len());
+    tag
+}
+
+// AES GCM with a 96-bit IV and a 128-bit tag. See https://datatracker.ietf.org/doc/html/rfc7518#section-5.3
+fn gcm_encrypt<C>(cek: &[u8], iv: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ValidationError>
+where
+    AesGcm<C, U12>: KeyInit + Aead,
+{
+    let cipher = AesGcm::<C, U12>::new_from_slice(cek).map_err(|e| ValidationError::InvalidKey(e.to_string()))?;
+    let mut ciphertext = cipher
+        .encrypt(GenericArray::from_slice(iv), Payload { msg: plaintext, aad })
+        .map_err(|_| ValidationError::MalformedToken("payload is too large to encrypt".to_string()))?;
+    let tag = ciphertext.split_off(ciphertext.len() - 16);
+    Ok((ciphertext, tag))
+}
+
+fn gcm_decrypt<C>(cek: &[u8], iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, ValidationError>
+where
+    AesGcm<C, U12>: KeyInit + Aead,
+{
+    let cipher = AesGcm::<C, U12>::new_from_slice(cek).map_err(|_| ValidationError::DecryptionFailed)?;
+    let msg = [ciphertext, tag].concat();
+    cipher
+        .decrypt(GenericArray::from_slice(iv), Payload { msg: &msg, aad })
+        .map_err(|_| ValidationError::DecryptionFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc7518#appendix-B.1
+    #[test]
+    fn matches_rfc7518_aes_128_cbc_hmac_sha_256_example() {
+        let cek: Vec<u8> = (0u8..32).collect();
+        let plaintext =
+            b"A cipher system must not be required to be secret, and it must be able to fall into the hands of the enemy without inconvenience";
+        let iv = hex("1a f3 8c 2d c2 b9 6f fd d8 66 94 09 23 41 bc 04");
+        let aad = b"The second principle of Auguste Kerckhoffs";
+        let ciphertext = hex(
+            "c8 0e df a3 2d df 39 d5 ef 00 c0 b4 68 83 42 79 a2 e4 6a 1b 80 49 f7 92 f7 6b fe 54 b9 03 a9 c9
+             a9 4a c9 b4 7a d2 65 5c 5f 10 f9 ae f7 14 27 e2 fc 6f 9b 3f 39 9a 22 14 89 f1 63 62 c7 03 23 36
+             09 d4 5a c6 98 64 e3 32 1c f8 29 35 ac 40 96 c8 6e 13 33 14 c5 40 19 e8 ca 79 80 df a4 b9 cf 1b
+             38 4c 48 6f 3a 54 c5 10 78 15 8e e5 d7 9d e5 9f bd 34 d8 48 b3 d6 95 50 a6 76 46 34 44 27 ad e5
+             4b 88 51 ff b5 98 f7 f8 00 74 b9 47 3c 82 e2 db",
+        );
+        let tag = hex("65 2c 3f a3 6b 0a 7c 5b 32 19 fa b3 a3 0b c1 c4");
+
+        assert_eq!(
+            encrypt(Encryption::A128CbcHs256, &cek, &iv, aad, plaintext).unwrap(),
+            (ciphertext.clone(), tag.clone())
+        );
+        assert_eq!(decrypt(Encryption::A128CbcHs256, &cek, &iv, aad, &ciphertext, &tag).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        for enc in [
+            Encryption::A128CbcHs256,
+            Encryption::A192CbcHs384,
+            Encryption::A256CbcHs512,
+            Encryption::A128GCM,
+            Encryption::A192GCM,
+            Encryption::A256GCM,
+        ] {
+            let cek = vec![9u8; enc.key_len()];
+            let iv = vec![3u8; enc.iv_len()];
+            let (ciphertext, tag) = encrypt(enc, &cek, &iv, b"aad", b"claims").unwrap();
+            assert_eq!(decrypt(enc, &cek, &iv, b"aad", &ciphertext, &tag).unwrap(), b"claims", "{enc}");
+
+            let err = decrypt(enc, &cek, &iv, b"aae", &ciphertext, &tag).unwrap_err();
+            assert_eq!(err, ValidationError::DecryptionFailed, "{enc}");
+            let mut forged = ciphertext.clone();
+            forged[0] ^= 1;
+            let err = decrypt(enc, &cek, &iv, b"aad", &forged, &tag).unwrap_err();
+            assert_eq!(err, ValidationError::DecryptionFailed, "{enc}");
+            let err = decrypt(enc, &cek[1..], &iv, b"aad", &ciphertext, &tag).unwrap_err();
+            assert_eq!(err, ValidationError::DecryptionFailed, "{enc}");
+        }
+    }
+}
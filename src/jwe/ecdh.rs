@@ -0,0 +1,234 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use p256::{
+    elliptic_curve::sec1::ToEncodedPoint,
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+};
+use rand_core::OsRng;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    jws::{Jwk, KeyParams},
+    ValidationError,
+};
+
+// EcPublicKey is the recipient's public key for ECDH-ES key agreement. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.6
+#[derive(Clone)]
+pub(crate) enum EcPublicKey {
+    P256(p256::PublicKey),
+    P384(p384::PublicKey),
+    P521(p521::PublicKey),
+}
+
+// EcSecretKey is the recipient's private key for ECDH-ES key agreement.
+#[derive(Clone)]
+pub(crate) enum EcSecretKey {
+    P256(p256::SecretKey),
+    P384(p384::SecretKey),
+    P521(p521::SecretKey),
+}
+
+fn invalid_key<E: ToString>(e: E) -> ValidationError {
+    ValidationError::InvalidKey(e.to_string())
+}
+
+impl EcPublicKey {
+    // Parses a SubjectPublicKeyInfo (`PUBLIC KEY`) PEM document for a P-256, P-384 or P-521 key.
+    pub(crate) fn from_pem(pem: &str) -> Result<Self, ValidationError> {
+        if let Ok(key) = p256::PublicKey::from_public_key_pem(pem) {
+            return Ok(EcPublicKey::P256(key));
+        }
+        if let Ok(key) = p384::PublicKey::from_public_key_pem(pem) {
+            return Ok(EcPublicKey::P384(key));
+        }
+        p521::PublicKey::from_public_key_pem(pem)
+            .map(EcPublicKey::P521)
+            .map_err(|_| invalid_key("expected a P-256, P-384 or P-521 public key"))
+    }
+
+    // Parses a SubjectPublicKeyInfo DER document for a P-256, P-384 or P-521 key.
+    pub(crate) fn from_der(der: &[u8]) -> Result<Self, ValidationError> {
+        if let Ok(key) = p256::PublicKey::from_public_key_der(der) {
+            return Ok(EcPublicKey::P256(key));
+        }
+        if let Ok(key) = p384::PublicKey::from_public_key_der(der) {
+            return Ok(EcPublicKey::P384(key));
+        }
+        p521::PublicKey::from_public_key_der(der)
+            .map(EcPublicKey::P521)
+            .map_err(|_| invalid_key("expected a P-256, P-384 or P-521 public key"))
+    }
+
+    // Parses an EC JWK, such as the `epk` Header Parameter. The point must lie on the named curve.
+    pub(crate) fn from_jwk(jwk: &Jwk) -> Result<Self, ValidationError> {
+        let KeyParams::Ec { ref crv, ref x, ref y } = jwk.params else {
+            return Err(invalid_key("expected an EC key"));
+        };
+        let decode = |value: &str| URL_SAFE_NO_PAD.decode(value).map_err(invalid_key);
+        let (x, y) = (decode(x)?, decode(y)?);
+        let size = match crv.as_str() {
+            "P-256" => 32,
+            "P-384" => 48,
+            "P-521" => 66,
+            _ => return Err(invalid_key(format!("unsupported curve `{crv}`"))),
+        };
+        if x.len() != size || y.len() != size {
+            return Err(invalid_key(format!("coordinates do not match curve `{crv}`")));
+        }
+        let sec1 = [&[0x04][..], &x, &y].concat();
+        match size {
+            32 => p256::PublicKey::from_sec1_bytes(&sec1).map(EcPublicKey::P256).map_err(invalid_key),
+            48 => p384::PublicKey::from_sec1_bytes(&sec1).map(EcPublicKey::P384).map_err(invalid_key),
+            _ => p521::PublicKey::from_sec1_bytes(&sec1).map(EcPublicKey::P521).map_err(invalid_key),
+        }
+    }
+
+    // Encodes the key as an EC JWK carrying only `crv`, `x` and `y`.
+    pub(crate) fn to_jwk(&self) -> Jwk {
+        let (crv, point) = match self {
+            EcPublicKey::P256(key) => ("P-256", key.to_encoded_point(false).as_bytes().to_vec()),
+            EcPublicKey::P384(key) => ("P-384", key.to_encoded_point(false).as_bytes().to_vec()),
+            EcPublicKey::P521(key) => ("P-521", key.to_encoded_point(false).as_bytes().to_vec()),
+        };
+        let (x, y) = point[1..].split_at((point.len() - 1) / 2);
+        Jwk {
+            params: KeyParams::Ec {
+                crv: crv.to_string(),
+                x: URL_SAFE_NO_PAD.encode(x),
+                y: URL_SAFE_NO_PAD.encode(y),
+            },
+            public_key_use: None,
+            key_operations: None,
+            algorithm: None,
+            key_id: None,
+        }
+    }
+
+    // Generates an ephemeral key pair on this key's curve, returning its public half and the shared secret Z.
+    pub(crate) fn agree_ephemeral(&self) -> (EcPublicKey, Vec<u8>) {
+        match self {
+            EcPublicKey::P256(key) => {
+                let ephemeral = p256::ecdh::EphemeralSecret::random(&mut OsRng);
+                let z = ephemeral.diffie_hellman(key).raw_secret_bytes().to_vec();
+                (EcPublicKey::P256(ephemeral.public_key()), z)
+            }
+            EcPublicKey::P384(key) => {
+                let ephemeral = p384::ecdh::EphemeralSecret::random(&mut OsRng);
+                let z = ephemeral.diffie_hellman(key).raw_secret_bytes().to_vec();
+                (EcPublicKey::P384(ephemeral.public_key()), z)
+            }
+            EcPublicKey::P521(key) => {
+                let ephemeral = p521::ecdh::EphemeralSecret::random(&mut OsRng);
+                let z = ephemeral.diffie_hellman(key).raw_secret_bytes().to_vec();
+                (EcPublicKey::P521(ephemeral.public_key()), z)
+            }
+        }
+    }
+}
+
+impl EcSecretKey {
+    // Parses a PKCS#8 (`PRIVATE KEY`) or SEC1 (`EC PRIVATE KEY`) PEM document for a P-256, P-384 or P-521 key.
+    pub(crate) fn from_pem(pem: &str) -> Result<Self, ValidationError> {
+        if let Ok(key) = p256::SecretKey::from_pkcs8_pem(pem).or_else(|_| p256::SecretKey::from_sec1_pem(pem)) {
+            return Ok(EcSecretKey::P256(key));
+        }
+        if let Ok(key) = p384::SecretKey::from_pkcs8_pem(pem).or_else(|_| p384::SecretKey::from_sec1_pem(pem)) {
+            return Ok(EcSecretKey::P384(key));
+        }
+        p521::SecretKey::from_pkcs8_pem(pem)
+            .or_else(|_| p521::SecretKey::from_sec1_pem(pem))
+            .map(EcSecretKey::P521)
+            .map_err(|_| invalid_key("expected a P-256, P-384 or P-521 private key"))
+    }
+
+    // Parses a PKCS#8 or SEC1 DER document for a P-256, P-384 or P-521 key.
+    pub(crate) fn from_der(der: &[u8]) -> Result<Self, ValidationError> {
+        if let Ok(key) = p256::SecretKey::from_pkcs8_der(der).or_else(|_| p256::SecretKey::from_sec1_der(der)) {
+            return Ok(EcSecretKey::P256(key));
+        }
+        if let Ok(key) = p384::SecretKey::from_pkcs8_der(der).or_else(|_| p384::SecretKey::from_sec1_der(der)) {
+            return Ok(EcSecretKey::P384(key));
+        }
+        p521::SecretKey::from_pkcs8_der(der)
+            .or_else(|_| p521::SecretKey::from_sec1_der(der))
+            .map(EcSecretKey::P521)
+            .map_err(|_| invalid_key("expected a P-256, P-384 or P-521 private key"))
+    }
+
+    // Computes the shared secret Z with the sender's ephemeral public key.
+    pub(crate) fn agree(&self, ephemeral: &EcPublicKey) -> Result<Vec<u8>, ValidationError> {
+        match (self, ephemeral) {
+            (EcSecretKey::P256(key), EcPublicKey::P256(epk)) => Ok(p256::ecdh::diffie_hellman(key.to_nonzero_scalar(), epk.as_affine())
+                .raw_secret_bytes()
+                .to_vec()),
+            (EcSecretKey::P384(key), EcPublicKey::P384(epk)) => Ok(p384::ecdh::diffie_hellman(key.to_nonzero_scalar(), epk.as_affine())
+                .raw_secret_bytes()
+                .to_vec()),
+            (EcSecretKey::P521(key), EcPublicKey::P521(epk)) => Ok(p521::ecdh::diffie_hellman(key.to_nonzero_scalar(), epk.as_affine())
+                .raw_secret_bytes()
+                .to_vec()),
+            _ => Err(ValidationError::MalformedToken("`epk` is not on the key's curve".to_string())),
+        }
+    }
+}
+
+// Derives `key_len` bytes from the shared secret Z with the Concat KDF, as specified for ECDH-ES.
+// See https://datatracker.ietf.org/doc/html/rfc7518#section-4.6.2
+pub(crate) fn concat_kdf(z: &[u8], algorithm_id: &str, party_u_info: &[u8], party_v_info: &[u8], key_len: usize) -> Vec<u8> {
+    let mut other_info = Vec::new();
+    for field in [algorithm_id.as_bytes(), party_u_info, party_v_info] {
+        other_info.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        other_info.extend_from_slice(field);
+    }
+    other_info.extend_from_slice(&(key_len as u32 * 8).to_be_bytes());
+
+    let mut key = Vec::with_capacity(key_len);
+    for counter in 1u32.. {
+        if key.len() >= key_len {
+            break;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(counter.to_be_bytes());
+        hasher.update(z);
+        hasher.update(&other_info);
+        key.extend_from_slice(&hasher.finalize());
+    }
+    key.truncate(key_len);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://datatracker.ietf.org/doc/html/rfc7518#appendix-C
+    #[test]
+    fn matches_rfc7518_key_agreement_example() {
+        let alice: Jwk = serde_json::from_str(
+            r#"{"kty":"EC","crv":"P-256","x":"gI0GAILBdu7T53akrFmMyGcsF3n5dO7MmwNBHKW5SV0","y":"SLW_xSffzlPWrHEVI30DHM_4egVwt3NQqeUD7nMFpps"}"#,
+        )
+        .unwrap();
+        let bob_d = URL_SAFE_NO_PAD.decode("VEmDZpDXXK8p8N0Cndsxs924q6nS1RXFASRl6BfUqdw").unwrap();
+        let bob = EcSecretKey::P256(p256::SecretKey::from_slice(&bob_d).unwrap());
+
+        let z = bob.agree(&EcPublicKey::from_jwk(&alice).unwrap()).unwrap();
+        let key = concat_kdf(&z, "A128GCM", b"Alice", b"Bob", 16);
+        assert_eq!(URL_SAFE_NO_PAD.encode(key), "VqqN6vgjbSBcIijNcacQGg");
+
+        let EcSecretKey::P256(ref bob_key) = bob else { unreachable!() };
+        let bob_public = EcPublicKey::P256(bob_key.public_key());
+        let jwk = bob_public.to_jwk();
+        assert_eq!(
+            serde_json::to_string(&jwk).unwrap(),
+            r#"{"kty":"EC","crv":"P-256","x":"weNJy2HscCSM6AEDTDg04biOvhFhyyWvOHQfeF_PxMQ","y":"e8lnCO-AlStT-NJVX-crhB7QRYhiix03illJOVAOyck"}"#
+        );
+    }
+
+    #[test]
+    fn rejects_points_off_the_curve() {
+        let jwk: Jwk = serde_json::from_str(
+            r#"{"kty":"EC","crv":"P-256","x":"gI0GAILBdu7T53akrFmMyGcsF3n5dO7MmwNBHKW5SV0","y":"SLW_xSffzlPWrHEVI30DHM_4egVwt3NQqeUD7nMFppo"}"#,
+        )
+        .unwrap();
+        assert!(matches!(EcPublicKey::from_jwk(&jwk), Err(ValidationError::InvalidKey(_))));
+    }
+}
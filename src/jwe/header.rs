@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{Encryption, KeyAlgorithm};
+use crate::{jws::Jwk, ValidationError};
+
+// Header Parameter names defined for JWE by RFC 7516 and RFC 7518, which must not be listed in `crit`.
+// See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.13
+const REGISTERED_NAMES: &[&str] = &[
+    "alg", "enc", "zip", "jku", "jwk", "kid", "x5u", "x5c", "x5t", "x5t#S256", "typ", "cty", "crit", "epk", "apu", "apv", "iv", "tag", "p2s", "p2c",
+];
+
+// Header is the JOSE Header of a JWE. See https://datatracker.ietf.org/doc/html/rfc7516#section-4
+//
+// Like the JWS header, any parameter without a typed field is kept in `extensions`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    // the `alg` (Algorithm) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.1
+    #[serde(rename = "alg")]
+    pub algorithm: KeyAlgorithm,
+
+    // the `enc` (Encryption Algorithm) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.2
+    #[serde(rename = "enc")]
+    pub encryption: Encryption,
+
+    // the `zip` (Compression Algorithm) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.3
+    #[serde(rename = "zip", default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+
+    // the `kid` (Key ID) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.6
+    #[serde(rename = "kid", default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+
+    // the `typ` (Type) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.11
+    #[serde(rename = "typ", default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+
+    // the `cty` (Content Type) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.12
+    #[serde(rename = "cty", default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    // the `crit` (Critical) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7516#section-4.1.13
+    #[serde(rename = "crit", default, skip_serializing_if = "Option::is_none")]
+    pub critical: Option<Vec<String>>,
+
+    // the `epk` (Ephemeral Public Key) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.6.1.1
+    #[serde(rename = "epk", default, skip_serializing_if = "Option::is_none")]
+    pub ephemeral_key: Option<Jwk>,
+
+    // the `apu` (Agreement PartyUInfo) Header Parameter, base64url-encoded. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.6.1.2
+    #[serde(rename = "apu", default, skip_serializing_if = "Option::is_none")]
+    pub party_u_info: Option<String>,
+
+    // the `apv` (Agreement PartyVInfo) Header Parameter, base64url-encoded. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.6.1.3
+    #[serde(rename = "apv", default, skip_serializing_if = "Option::is_none")]
+    pub party_v_info: Option<String>,
+
+    // every other Header Parameter, keyed by name.
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Header {
+    // Creates a header for `algorithm` and `encryption` with `typ` set to `JWT`.
+    pub fn new(algorithm: KeyAlgorithm, encryption: Encryption) -> Self {
+        Header {
+            algorithm,
+            encryption,
+            compression: None,
+            key_id: None,
+            token_type: Some("JWT".to_string()),
+            content_type: None,
+            critical: None,
+            ephemeral_key: None,
+            party_u_info: None,
+            party_v_info: None,
+            extensions: Map::new(),
+        }
+    }
+
+    // Reports whether the `typ` parameter names `expected`, with the same rules as the JWS header.
+    pub fn has_type(&self, expected: &str) -> bool {
+        crate::jws::type_matches(self.token_type.as_deref(), expected)
+    }
+
+    // Applies the `crit` rules, with the same rules as the JWS header.
+    pub fn verify_critical(&self, understood: &[String]) -> Result<(), ValidationError> {
+        crate::jws::verify_critical(self.critical.as_deref(), &self.extensions, REGISTERED_NAMES, understood)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://datatracker.ietf.org/doc/html/rfc7518#appendix-C
+    #[test]
+    fn round_trips_key_agreement_parameters() {
+        let input = r#"{"alg":"ECDH-ES","enc":"A128GCM","epk":{"kty":"EC","crv":"P-256","x":"gI0GAILBdu7T53akrFmMyGcsF3n5dO7MmwNBHKW5SV0","y":"SLW_xSffzlPWrHEVI30DHM_4egVwt3NQqeUD7nMFpps"},"apu":"QWxpY2U","apv":"Qm9i"}"#;
+        let header: Header = serde_json::from_str(input).unwrap();
+        assert_eq!(header.algorithm, KeyAlgorithm::EcdhEs);
+        assert_eq!(header.encryption, Encryption::A128GCM);
+        assert_eq!(header.ephemeral_key.as_ref().unwrap().params.key_type(), "EC");
+        assert_eq!(header.party_u_info.as_deref(), Some("QWxpY2U"));
+        assert_eq!(serde_json::to_string(&header).unwrap(), input);
+
+        let header: Header = serde_json::from_str(r#"{"alg":"dir","enc":"A128GCM","crit":["iv"],"iv":"AxY8DCtDaGlsbGljb3RoZQ"}"#).unwrap();
+        assert_eq!(header.verify_critical(&["iv".to_string()]).unwrap_err().reason(), "malformed_token");
+    }
+}
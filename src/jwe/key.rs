@@ -0,0 +1,352 @@
+use std::fmt;
+
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand_core::OsRng;
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey},
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    traits::PublicKeyParts,
+    Oaep, RsaPrivateKey, RsaPublicKey,
+};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use super::{
+    ecdh::{concat_kdf, EcPublicKey, EcSecretKey},
+    Encryption, Header, KeyAlgorithm,
+};
+use crate::ValidationError;
+
+// RFC 7518 requires RSA keys of at least 2048 bits. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.2
+const MIN_RSA_KEY_BITS: usize = 2048;
+
+// EncryptionKey is the recipient's public key, or the shared secret, used to encrypt a token.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    inner: EncryptionKeyInner,
+}
+
+#[derive(Clone)]
+enum EncryptionKeyInner {
+    Secret(Vec<u8>),
+    Rsa(Box<RsaPublicKey>),
+    Ec(Box<EcPublicKey>),
+}
+
+// DecryptionKey is the recipient's private key, or the shared secret, used to decrypt a token.
+//
+// Each key carries the algorithms it accepts, so a token can never pick its own: by default those are the key
+// management algorithms the key is meant for (RSA-OAEP and RSA-OAEP-256 for RSA, the ECDH-ES algorithms for EC,
+// `dir` and the AES Key Wrap matching its length for a secret) with any content encryption. `with_algorithms`
+// and `with_encryptions` narrow them further.
+#[derive(Clone)]
+pub struct DecryptionKey {
+    inner: DecryptionKeyInner,
+    algorithms: Vec<KeyAlgorithm>,
+    encryptions: Vec<Encryption>,
+}
+
+#[derive(Clone)]
+enum DecryptionKeyInner {
+    Secret(Vec<u8>),
+    Rsa(Box<RsaPrivateKey>),
+    Ec(Box<EcSecretKey>),
+}
+
+fn invalid_key<E: ToString>(e: E) -> ValidationError {
+    ValidationError::InvalidKey(e.to_string())
+}
+
+fn check_rsa_size<K: PublicKeyParts>(key: K) -> Result<K, ValidationError> {
+    if key.n().bits() < MIN_RSA_KEY_BITS {
+        return Err(invalid_key(format!("RSA keys must be at least {MIN_RSA_KEY_BITS} bits")));
+    }
+    Ok(key)
+}
+
+impl EncryptionKey {
+    // Creates a key for `dir` or the A128KW, A192KW and A256KW algorithms from a shared secret.
+    pub fn from_secret(secret: &[u8]) -> Self {
+        EncryptionKey {
+            inner: EncryptionKeyInner::Secret(secret.to_vec()),
+        }
+    }
+
+    // Loads an RSA public key for RSA-OAEP and RSA-OAEP-256 from a SubjectPublicKeyInfo or PKCS#1 PEM document.
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, ValidationError> {
+        let key = RsaPublicKey::from_public_key_pem(pem)
+            .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+            .map_err(invalid_key)?;
+        Ok(EncryptionKey {
+            inner: EncryptionKeyInner::Rsa(Box::new(check_rsa_size(key)?)),
+        })
+    }
+
+    // Loads an RSA public key for RSA-OAEP and RSA-OAEP-256 from a SubjectPublicKeyInfo or PKCS#1 DER document.
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, ValidationError> {
+        let key = RsaPublicKey::from_public_key_der(der)
+            .or_else(|_| RsaPublicKey::from_pkcs1_der(der))
+            .map_err(invalid_key)?;
+        Ok(EncryptionKey {
+            inner: EncryptionKeyInner::Rsa(Box::new(check_rsa_size(key)?)),
+        })
+    }
+
+    // Loads an EC public key for the ECDH-ES algorithms from a SubjectPublicKeyInfo PEM document.
+    pub fn from_ec_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(EncryptionKey {
+            inner: EncryptionKeyInner::Ec(Box::new(EcPublicKey::from_pem(pem)?)),
+        })
+    }
+
+    // Loads an EC public key for the ECDH-ES algorithms from a SubjectPublicKeyInfo DER document.
+    pub fn from_ec_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(EncryptionKey {
+            inner: EncryptionKeyInner::Ec(Box::new(EcPublicKey::from_der(der)?)),
+        })
+    }
+
+    // Determines the Content Encryption Key for `header` and the JWE Encrypted Key that conveys it. `random_cek` is
+    // used unless the algorithm derives the key itself (`dir` and `ECDH-ES`). The ECDH-ES algorithms add `epk` to `header`.
+    pub(crate) fn encrypt_cek(&self, header: &mut Header, random_cek: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>), ValidationError> {
+        let alg = header.algorithm;
+        match (alg, &self.inner) {
+            (KeyAlgorithm::Direct, EncryptionKeyInner::Secret(secret)) => {
+                if secret.len() != header.encryption.key_len() {
+                    return Err(invalid_key(format!(
+                        "{} requires a key of {} bytes",
+                        header.encryption,
+                        header.encryption.key_len()
+                    )));
+                }
+                Ok((secret.clone(), Vec::new()))
+            }
+            (KeyAlgorithm::A128KW | KeyAlgorithm::A192KW | KeyAlgorithm::A256KW, EncryptionKeyInner::Secret(kek)) => {
+                let encrypted_key = wrap(alg, kek, &random_cek)?;
+                Ok((random_cek, encrypted_key))
+            }
+            (KeyAlgorithm::RsaOaep, EncryptionKeyInner::Rsa(key)) => {
+                let encrypted_key = key.encrypt(&mut OsRng, Oaep::new::<Sha1>(), &random_cek).map_err(invalid_key)?;
+                Ok((random_cek, encrypted_key))
+            }
+            (KeyAlgorithm::RsaOaep256, EncryptionKeyInner::Rsa(key)) => {
+                let encrypted_key = key.encrypt(&mut OsRng, Oaep::new::<Sha256>(), &random_cek).map_err(invalid_key)?;
+                Ok((random_cek, encrypted_key))
+            }
+            (
+                KeyAlgorithm::EcdhEs | KeyAlgorithm::EcdhEsA128KW | KeyAlgorithm::EcdhEsA192KW | KeyAlgorithm::EcdhEsA256KW,
+                EncryptionKeyInner::Ec(key),
+            ) => {
+                let (ephemeral, z) = key.agree_ephemeral();
+                header.ephemeral_key = Some(ephemeral.to_jwk());
+                let derived = derive_key(header, &z)?;
+                if alg == KeyAlgorithm::EcdhEs {
+                    return Ok((derived, Vec::new()));
+                }
+                let encrypted_key = wrap(alg, &derived, &random_cek)?;
+                Ok((random_cek, encrypted_key))
+            }
+            _ => Err(invalid_key(format!("key cannot be used with {alg}"))),
+        }
+    }
+}
+
+impl DecryptionKey {
+    // Creates a key for `dir` or the A128KW, A192KW and A256KW algorithms from a shared secret.
+    pub fn from_secret(secret: &[u8]) -> Self {
+        DecryptionKey::new(DecryptionKeyInner::Secret(secret.to_vec()))
+    }
+
+    // Loads an RSA private key for RSA-OAEP and RSA-OAEP-256 from a PKCS#8 or PKCS#1 PEM document.
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, ValidationError> {
+        let key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map_err(invalid_key)?;
+        Ok(DecryptionKey::new(DecryptionKeyInner::Rsa(Box::new(check_rsa_size(key)?))))
+    }
+
+    // Loads an RSA private key for RSA-OAEP and RSA-OAEP-256 from a PKCS#8 or PKCS#1 DER document.
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, ValidationError> {
+        let key = RsaPrivateKey::from_pkcs8_der(der)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_der(der))
+            .map_err(invalid_key)?;
+        Ok(DecryptionKey::new(DecryptionKeyInner::Rsa(Box::new(check_rsa_size(key)?))))
+    }
+
+    // Loads an EC private key for the ECDH-ES algorithms from a PKCS#8 or SEC1 PEM document.
+    pub fn from_ec_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(DecryptionKey::new(DecryptionKeyInner::Ec(Box::new(EcSecretKey::from_pem(pem)?))))
+    }
+
+    // Loads an EC private key for the ECDH-ES algorithms from a PKCS#8 or SEC1 DER document.
+    pub fn from_ec_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(DecryptionKey::new(DecryptionKeyInner::Ec(Box::new(EcSecretKey::from_der(der)?))))
+    }
+
+    fn new(inner: DecryptionKeyInner) -> Self {
+        let algorithms = match inner {
+            DecryptionKeyInner::Secret(ref secret) => {
+                let wrap = [KeyAlgorithm::A128KW, KeyAlgorithm::A192KW, KeyAlgorithm::A256KW];
+                let wrap = wrap.into_iter().filter(|alg| alg.key_wrap_len() == Some(secret.len()));
+                std::iter::once(KeyAlgorithm::Direct).chain(wrap).collect()
+            }
+            DecryptionKeyInner::Rsa(_) => vec![KeyAlgorithm::RsaOaep, KeyAlgorithm::RsaOaep256],
+            DecryptionKeyInner::Ec(_) => vec![
+                KeyAlgorithm::EcdhEs,
+                KeyAlgorithm::EcdhEsA128KW,
+                KeyAlgorithm::EcdhEsA192KW,
+                KeyAlgorithm::EcdhEsA256KW,
+            ],
+        };
+        let encryptions = vec![
+            Encryption::A128CbcHs256,
+            Encryption::A192CbcHs384,
+            Encryption::A256CbcHs512,
+            Encryption::A128GCM,
+            Encryption::A192GCM,
+            Encryption::A256GCM,
+        ];
+        DecryptionKey {
+            inner,
+            algorithms,
+            encryptions,
+        }
+    }
+
+    // Restricts the key to the key management `algorithms`. Tokens using any other `alg` are rejected with
+    // `ValidationError::EncryptionNotAllowed`, even if the key could decrypt them.
+    pub fn with_algorithms(mut self, algorithms: impl IntoIterator<Item = KeyAlgorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
+    // Restricts the key to the content `encryptions`, rejecting tokens with any other `enc` in the same way.
+    pub fn with_encryptions(mut self, encryptions: impl IntoIterator<Item = Encryption>) -> Self {
+        self.encryptions = encryptions.into_iter().collect();
+        self
+    }
+
+    // Returns the key management algorithms this key accepts.
+    pub fn algorithms(&self) -> &[KeyAlgorithm] {
+        &self.algorithms
+    }
+
+    // Returns the content encryption algorithms this key accepts.
+    pub fn encryptions(&self) -> &[Encryption] {
+        &self.encryptions
+    }
+
+    // Rejects a token whose `alg` or `enc` this key does not accept.
+    pub(crate) fn check_algorithms(&self, header: &Header) -> Result<(), ValidationError> {
+        if !self.algorithms.contains(&header.algorithm) {
+            return Err(ValidationError::EncryptionNotAllowed(header.algorithm.name().to_string()));
+        }
+        if !self.encryptions.contains(&header.encryption) {
+            return Err(ValidationError::EncryptionNotAllowed(header.encryption.name().to_string()));
+        }
+        Ok(())
+    }
+
+    // Recovers the Content Encryption Key from the JWE Encrypted Key. Failures to unwrap or decrypt it are reported
+    // as `DecryptionFailed`, without detail.
+    pub(crate) fn decrypt_cek(&self, header: &Header, encrypted_key: &[u8]) -> Result<Vec<u8>, ValidationError> {
+        let alg = header.algorithm;
+        let cek = match (alg, &self.inner) {
+            (KeyAlgorithm::Direct, DecryptionKeyInner::Secret(secret)) => {
+                if !encrypted_key.is_empty() {
+                    return Err(ValidationError::MalformedToken(
+                        "`dir` tokens must not carry an encrypted key".to_string(),
+                    ));
+                }
+                secret.clone()
+            }
+            (KeyAlgorithm::A128KW | KeyAlgorithm::A192KW | KeyAlgorithm::A256KW, DecryptionKeyInner::Secret(kek)) => unwrap(alg, kek, encrypted_key)?,
+            (KeyAlgorithm::RsaOaep, DecryptionKeyInner::Rsa(key)) => key
+                .decrypt(Oaep::new::<Sha1>(), encrypted_key)
+                .map_err(|_| ValidationError::DecryptionFailed)?,
+            (KeyAlgorithm::RsaOaep256, DecryptionKeyInner::Rsa(key)) => key
+                .decrypt(Oaep::new::<Sha256>(), encrypted_key)
+                .map_err(|_| ValidationError::DecryptionFailed)?,
+            (
+                KeyAlgorithm::EcdhEs | KeyAlgorithm::EcdhEsA128KW | KeyAlgorithm::EcdhEsA192KW | KeyAlgorithm::EcdhEsA256KW,
+                DecryptionKeyInner::Ec(key),
+            ) => {
+                let ephemeral = header
+                    .ephemeral_key
+                    .as_ref()
+                    .ok_or_else(|| ValidationError::MalformedToken("missing `epk` header".to_string()))?;
+                let ephemeral =
+                    EcPublicKey::from_jwk(ephemeral).map_err(|e| ValidationError::MalformedToken(format!("invalid `epk` header: {e}")))?;
+                let derived = derive_key(header, &key.agree(&ephemeral)?)?;
+                if alg != KeyAlgorithm::EcdhEs {
+                    unwrap(alg, &derived, encrypted_key)?
+                } else if encrypted_key.is_empty() {
+                    derived
+                } else {
+                    return Err(ValidationError::MalformedToken(
+                        "`ECDH-ES` tokens must not carry an encrypted key".to_string(),
+                    ));
+                }
+            }
+            _ => return Err(invalid_key(format!("key cannot be used with {alg}"))),
+        };
+        if cek.len() != header.encryption.key_len() {
+            return Err(ValidationError::DecryptionFailed);
+        }
+        Ok(cek)
+    }
+}
+
+// Derives the key agreed on with ECDH-ES: the Content Encryption Key for `ECDH-ES`, or the key wrapping key for
+// `ECDH-ES+A*KW`. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.6.2
+fn derive_key(header: &Header, z: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    let decode = |name: &str, value: &Option<String>| match value {
+        Some(value) => URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|e| ValidationError::MalformedToken(format!("invalid `{name}` header: {e}"))),
+        None => Ok(Vec::new()),
+    };
+    let party_u_info = decode("apu", &header.party_u_info)?;
+    let party_v_info = decode("apv", &header.party_v_info)?;
+    let (algorithm_id, key_len) = match header.algorithm.key_wrap_len() {
+        Some(len) => (header.algorithm.name(), len),
+        None => (header.encryption.name(), header.encryption.key_len()),
+    };
+    Ok(concat_kdf(z, algorithm_id, &party_u_info, &party_v_info, key_len))
+}
+
+// Wraps the Content Encryption Key with AES Key Wrap. See https://datatracker.ietf.org/doc/html/rfc7518#section-4.4
+fn wrap(alg: KeyAlgorithm, kek: &[u8], cek: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    let result = match (alg.key_wrap_len(), kek.len()) {
+        (Some(16), 16) => KekAes128::try_from(kek).and_then(|kek| kek.wrap_vec(cek)),
+        (Some(24), 24) => KekAes192::try_from(kek).and_then(|kek| kek.wrap_vec(cek)),
+        (Some(32), 32) => KekAes256::try_from(kek).and_then(|kek| kek.wrap_vec(cek)),
+        (Some(len), _) => return Err(invalid_key(format!("{alg} requires a key of {len} bytes"))),
+        (None, _) => return Err(invalid_key(format!("key cannot be used with {alg}"))),
+    };
+    result.map_err(invalid_key)
+}
+
+fn unwrap(alg: KeyAlgorithm, kek: &[u8], encrypted_key: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    let result = match (alg.key_wrap_len(), kek.len()) {
+        (Some(16), 16) => KekAes128::try_from(kek).and_then(|kek| kek.unwrap_vec(encrypted_key)),
+        (Some(24), 24) => KekAes192::try_from(kek).and_then(|kek| kek.unwrap_vec(encrypted_key)),
+        (Some(32), 32) => KekAes256::try_from(kek).and_then(|kek| kek.unwrap_vec(encrypted_key)),
+        (Some(len), _) => return Err(invalid_key(format!("{alg} requires a key of {len} bytes"))),
+        (None, _) => return Err(invalid_key(format!("key cannot be used with {alg}"))),
+    };
+    result.map_err(|_| ValidationError::DecryptionFailed)
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for DecryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecryptionKey").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,145 @@
+//! JSON Web Encryption support for claim sets, as referenced at https://datatracker.ietf.org/doc/html/rfc7516.
+//!
+//! Only the JWE Compact Serialization is supported, behind the `jwe` cargo feature. The Content Encryption
+//! Key is managed with RSA-OAEP, RSA-OAEP-256, A128KW, A192KW, A256KW, dir, ECDH-ES or
+//! ECDH-ES+A128KW/A192KW/A256KW over P-256, P-384 and P-521, and the content is encrypted with
+//! A128CBC-HS256, A192CBC-HS384, A256CBC-HS512, A128GCM, A192GCM or A256GCM. Compressed (`zip`)
+//! tokens are not supported.
+//!
+//! With a `jws-*` feature enabled as well, `sign_and_encrypt` and `decrypt_and_verify` produce and consume
+//! Nested JWTs, whose claims are signed before being encrypted.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "jws")]
+pub use self::nested::{decrypt_and_verify, decrypt_and_verify_at, sign_and_encrypt, NestedTokenData};
+pub use self::{
+    compact::{decrypt, decrypt_at, encrypt, TokenData},
+    header::Header,
+    key::{DecryptionKey, EncryptionKey},
+};
+
+mod compact;
+mod content;
+mod ecdh;
+mod header;
+mod key;
+#[cfg(feature = "jws")]
+mod nested;
+
+// KeyAlgorithm is a JWE key management algorithm, used to determine the Content Encryption Key.
+// See https://datatracker.ietf.org/doc/html/rfc7518#section-4.1
+#[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAlgorithm {
+    #[serde(rename = "RSA-OAEP")]
+    RsaOaep,
+    #[serde(rename = "RSA-OAEP-256")]
+    RsaOaep256,
+    A128KW,
+    A192KW,
+    A256KW,
+    #[serde(rename = "dir")]
+    Direct,
+    #[serde(rename = "ECDH-ES")]
+    EcdhEs,
+    #[serde(rename = "ECDH-ES+A128KW")]
+    EcdhEsA128KW,
+    #[serde(rename = "ECDH-ES+A192KW")]
+    EcdhEsA192KW,
+    #[serde(rename = "ECDH-ES+A256KW")]
+    EcdhEsA256KW,
+}
+
+impl KeyAlgorithm {
+    // Returns the `alg` Header Parameter value, e.g. `RSA-OAEP`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyAlgorithm::RsaOaep => "RSA-OAEP",
+            KeyAlgorithm::RsaOaep256 => "RSA-OAEP-256",
+            KeyAlgorithm::A128KW => "A128KW",
+            KeyAlgorithm::A192KW => "A192KW",
+            KeyAlgorithm::A256KW => "A256KW",
+            KeyAlgorithm::Direct => "dir",
+            KeyAlgorithm::EcdhEs => "ECDH-ES",
+            KeyAlgorithm::EcdhEsA128KW => "ECDH-ES+A128KW",
+            KeyAlgorithm::EcdhEsA192KW => "ECDH-ES+A192KW",
+            KeyAlgorithm::EcdhEsA256KW => "ECDH-ES+A256KW",
+        }
+    }
+
+    // Returns the size in bytes of the AES Key Wrap key, for the algorithms that wrap the Content Encryption Key with one.
+    fn key_wrap_len(&self) -> Option<usize> {
+        match self {
+            KeyAlgorithm::A128KW | KeyAlgorithm::EcdhEsA128KW => Some(16),
+            KeyAlgorithm::A192KW | KeyAlgorithm::EcdhEsA192KW => Some(24),
+            KeyAlgorithm::A256KW | KeyAlgorithm::EcdhEsA256KW => Some(32),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Encryption is a JWE content encryption algorithm. See https://datatracker.ietf.org/doc/html/rfc7518#section-5.1
+#[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encryption {
+    #[serde(rename = "A128CBC-HS256")]
+    A128CbcHs256,
+    #[serde(rename = "A192CBC-HS384")]
+    A192CbcHs384,
+    #[serde(rename = "A256CBC-HS512")]
+    A256CbcHs512,
+    A128GCM,
+    A192GCM,
+    A256GCM,
+}
+
+impl Encryption {
+    // Returns the `enc` Header Parameter value, e.g. `A256GCM`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encryption::A128CbcHs256 => "A128CBC-HS256",
+            Encryption::A192CbcHs384 => "A192CBC-HS384",
+            Encryption::A256CbcHs512 => "A256CBC-HS512",
+            Encryption::A128GCM => "A128GCM",
+            Encryption::A192GCM => "A192GCM",
+            Encryption::A256GCM => "A256GCM",
+        }
+    }
+
+    // Returns the size in bytes of the Content Encryption Key.
+    pub fn key_len(&self) -> usize {
+        match self {
+            Encryption::A128CbcHs256 => 32,
+            Encryption::A192CbcHs384 => 48,
+            Encryption::A256CbcHs512 => 64,
+            Encryption::A128GCM => 16,
+            Encryption::A192GCM => 24,
+            Encryption::A256GCM => 32,
+        }
+    }
+
+    // Returns the size in bytes of the Initialization Vector.
+    pub fn iv_len(&self) -> usize {
+        match self {
+            Encryption::A128CbcHs256 | Encryption::A192CbcHs384 | Encryption::A256CbcHs512 => 16,
+            Encryption::A128GCM | Encryption::A192GCM | Encryption::A256GCM => 12,
+        }
+    }
+}
+
+impl fmt::Display for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
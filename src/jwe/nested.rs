@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    compact::{decrypt_payload, encrypt_payload},
+    DecryptionKey, EncryptionKey, Header,
+};
+use crate::{
+    jws::{self, SigningKey, VerifyingKey},
+    Clock, RegisteredClaims, SystemClock, Validation, ValidationError,
+};
+
+// NestedTokenData is a decrypted, verified and validated Nested JWT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NestedTokenData<C> {
+    // the header of the outermost JWE.
+    pub header: Header,
+    // the header of the innermost JWS.
+    pub signature_header: jws::Header,
+    pub claims: C,
+}
+
+// Signs `claims` into a JWS and encrypts that JWS into a JWE, producing a Nested JWT. The JWE header gets
+// `cty` set to `JWT`. See https://datatracker.ietf.org/doc/html/rfc7519#section-5.2
+pub fn sign_and_encrypt<C: Serialize>(
+    signature_header: &jws::Header,
+    header: &Header,
+    claims: &C,
+    signing_key: &SigningKey,
+    encryption_key: &EncryptionKey,
+) -> Result<String, ValidationError> {
+    let signed = jws::sign(signature_header, claims, signing_key)?;
+    let mut header = header.clone();
+    header.content_type = Some("JWT".to_string());
+    encrypt_payload(&header, signed.as_bytes(), encryption_key)
+}
+
+// Decrypts a Nested JWT, verifies the JWS inside it and validates its claims against `validation`.
+pub fn decrypt_and_verify<C>(
+    token: &str,
+    decryption_key: &DecryptionKey,
+    verifying_key: &VerifyingKey,
+    validation: &Validation,
+) -> Result<NestedTokenData<C>, ValidationError>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims>,
+{
+    decrypt_and_verify_at(token, decryption_key, verifying_key, validation, &SystemClock)
+}
+
+// Like `decrypt_and_verify`, but reads the current time from `clock`.
+//
+// Every layer must declare `cty: JWT`, and the innermost token must be a JWS: an encrypted claim set is rejected
+// even though it decrypts, since it carries no signature. At most `validation.max_nesting_depth` JWE layers are
+// removed. `typ` is only checked on the outermost header, while `crit` is checked on every header.
+pub fn decrypt_and_verify_at<C>(
+    token: &str,
+    decryption_key: &DecryptionKey,
+    verifying_key: &VerifyingKey,
+    validation: &Validation,
+    clock: &dyn Clock,
+) -> Result<NestedTokenData<C>, ValidationError>
+where
+    C: DeserializeOwned + AsRef<RegisteredClaims>,
+{
+    if validation.max_nesting_depth == 0 {
+        return Err(ValidationError::NestingTooDeep(0));
+    }
+    let inner_validation = Validation {
+        token_type: None,
+        ..validation.clone()
+    };
+    let (header, mut payload) = decrypt_payload(token, decryption_key, validation)?;
+    let mut content_type = header.content_type.clone();
+    let mut depth = 1;
+    loop {
+        if !jws::type_matches(content_type.as_deref(), "JWT") {
+            return Err(ValidationError::MalformedToken("expected `cty` to be `JWT` in a Nested JWT".to_string()));
+        }
+        let inner = std::str::from_utf8(&payload).map_err(|e| ValidationError::MalformedToken(e.to_string()))?;
+        match inner.split('.').count() {
+            3 => {
+                let token = jws::verify_with(inner, &inner_validation, clock, |_| Ok(Cow::Borrowed(verifying_key)))?;
+                return Ok(NestedTokenData {
+                    header,
+                    signature_header: token.header,
+                    claims: token.claims,
+                });
+            }
+            5 if depth < validation.max_nesting_depth => {
+                let (inner_header, inner_payload) = decrypt_payload(inner, decryption_key, &inner_validation)?;
+                content_type = inner_header.content_type;
+                payload = inner_payload;
+                depth += 1;
+            }
+            5 => return Err(ValidationError::NestingTooDeep(validation.max_nesting_depth)),
+            _ => return Err(ValidationError::MalformedToken("nested token is neither a JWS nor a JWE".to_string())),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use chrono::{TimeZone as _, Utc};
+
+    use super::*;
+    use crate::{
+        jwe::{encrypt, Encryption, KeyAlgorithm},
+        jws::Algorithm,
+        Claims,
+    };
+
+    fn claims() -> RegisteredClaims {
+        RegisteredClaims {
+            subject: "alice".to_string(),
+            expires_at: Utc.timestamp_opt(Utc::now().timestamp() + 300, 0).single(),
+            ..Default::default()
+        }
+    }
+
+    fn keys() -> (SigningKey, VerifyingKey, EncryptionKey, DecryptionKey) {
+        (
+            SigningKey::from_hmac_secret(&[7; 32]),
+            VerifyingKey::from_hmac_secret(&[7; 32]),
+            EncryptionKey::from_secret(&[9; 16]),
+            DecryptionKey::from_secret(&[9; 16]),
+        )
+    }
+
+    #[test]
+    fn round_trips_nested_tokens() {
+        let (signing_key, verifying_key, encryption_key, decryption_key) = keys();
+        let signature_header = jws::Header::new(Algorithm::HS256);
+        let header = Header::new(KeyAlgorithm::A128KW, Encryption::A256GCM);
+        let token = sign_and_encrypt(&signature_header, &header, &claims(), &signing_key, &encryption_key).unwrap();
+        assert_eq!(token.split('.').count(), 5);
+
+        let validation = Validation::new().with_subject("alice").with_token_type("JWT");
+        let nested: NestedTokenData<Claims> = decrypt_and_verify(&token, &decryption_key, &verifying_key, &validation).unwrap();
+        assert_eq!(nested.header.content_type.as_deref(), Some("JWT"));
+        assert_eq!(nested.signature_header, signature_header);
+        assert_eq!(nested.claims.registered, claims());
+
+        let err =
+            decrypt_and_verify::<RegisteredClaims>(&token, &decryption_key, &VerifyingKey::from_hmac_secret(&[8; 32]), &validation).unwrap_err();
+        assert_eq!(err, ValidationError::InvalidSignature);
+
+        let expired = RegisteredClaims {
+            expires_at: Utc.timestamp_opt(1_300_819_380, 0).single(),
+            ..claims()
+        };
+        let token = sign_and_encrypt(&signature_header, &header, &expired, &signing_key, &encryption_key).unwrap();
+        let err = decrypt_and_verify::<RegisteredClaims>(&token, &decryption_key, &verifying_key, &validation).unwrap_err();
+        assert_eq!(err.reason(), "token_expired");
+    }
+
+    #[test]
+    fn requires_a_signed_inner_token() {
+        let (_, verifying_key, encryption_key, decryption_key) = keys();
+        let validation = Validation::new();
+
+        let token = encrypt(&Header::new(KeyAlgorithm::A128KW, Encryption::A256GCM), &claims(), &encryption_key).unwrap();
+        let err = decrypt_and_verify::<RegisteredClaims>(&token, &decryption_key, &verifying_key, &validation).unwrap_err();
+        assert_eq!(err.reason(), "malformed_token");
+
+        let mut header = Header::new(KeyAlgorithm::A128KW, Encryption::A256GCM);
+        header.content_type = Some("JWT".to_string());
+        let token = encrypt(&header, &claims(), &encryption_key).unwrap();
+        let err = decrypt_and_verify::<RegisteredClaims>(&token, &decryption_key, &verifying_key, &validation).unwrap_err();
+        assert_eq!(err.reason(), "malformed_token");
+
+        let unsecured = format!("eyJhbGciOiJub25lIn0.{}.", URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims()).unwrap()));
+        let token = encrypt_payload(&header, unsecured.as_bytes(), &encryption_key).unwrap();
+        let err = decrypt_and_verify::<RegisteredClaims>(&token, &decryption_key, &verifying_key, &validation).unwrap_err();
+        assert_eq!(err, ValidationError::UnsecuredToken);
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let (signing_key, verifying_key, encryption_key, decryption_key) = keys();
+        let mut header = Header::new(KeyAlgorithm::A128KW, Encryption::A128GCM);
+        let token = sign_and_encrypt(&jws::Header::new(Algorithm::HS256), &header, &claims(), &signing_key, &encryption_key).unwrap();
+        header.content_type = Some("JWT".to_string());
+        let token = encrypt_payload(&header, token.as_bytes(), &encryption_key).unwrap();
+
+        let err = decrypt_and_verify::<RegisteredClaims>(&token, &decryption_key, &verifying_key, &Validation::new()).unwrap_err();
+        assert_eq!(err, ValidationError::NestingTooDeep(1));
+
+        // a depth of 0 rejects even a single JWE layer.
+        let single = sign_and_encrypt(&jws::Header::new(Algorithm::HS256), &header, &claims(), &signing_key, &encryption_key).unwrap();
+        let validation = Validation::new().with_max_nesting_depth(0);
+        let err = decrypt_and_verify::<RegisteredClaims>(&single, &decryption_key, &verifying_key, &validation).unwrap_err();
+        assert_eq!(err, ValidationError::NestingTooDeep(0));
+
+        let validation = Validation::new().with_max_nesting_depth(2);
+        let nested: NestedTokenData<RegisteredClaims> = decrypt_and_verify(&token, &decryption_key, &verifying_key, &validation).unwrap();
+        assert_eq!(nested.claims, claims());
+    }
+}
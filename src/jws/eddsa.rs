@@ -0,0 +1,102 @@
+use ed25519_dalek::{
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    Signature, Signer as _, SigningKey, VerifyingKey,
+};
+
+use super::Algorithm;
+use crate::ValidationError;
+
+fn invalid_key<E: ToString>(e: E) -> ValidationError {
+    ValidationError::InvalidKey(e.to_string())
+}
+
+pub(crate) fn signing_key_from_pem(pem: &str) -> Result<SigningKey, ValidationError> {
+    SigningKey::from_pkcs8_pem(pem).map_err(invalid_key)
+}
+
+pub(crate) fn signing_key_from_der(der: &[u8]) -> Result<SigningKey, ValidationError> {
+    SigningKey::from_pkcs8_der(der).map_err(invalid_key)
+}
+
+pub(crate) fn verifying_key_from_pem(pem: &str) -> Result<VerifyingKey, ValidationError> {
+    VerifyingKey::from_public_key_pem(pem).map_err(invalid_key)
+}
+
+pub(crate) fn verifying_key_from_der(der: &[u8]) -> Result<VerifyingKey, ValidationError> {
+    VerifyingKey::from_public_key_der(der).map_err(invalid_key)
+}
+
+// Reads a raw public key, which must be on the Ed25519 curve, the only one supported for EdDSA.
+// See https://datatracker.ietf.org/doc/html/rfc8037#section-3.1
+pub(crate) fn verifying_key_from_bytes(bytes: &[u8]) -> Result<VerifyingKey, ValidationError> {
+    let bytes = bytes.try_into().map_err(|_| invalid_key("Ed25519 public keys are 32 bytes"))?;
+    VerifyingKey::from_bytes(bytes).map_err(invalid_key)
+}
+
+pub(crate) fn sign(alg: Algorithm, key: &SigningKey, message: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    match alg {
+        Algorithm::EdDSA => Ok(key.sign(message).to_vec()),
+        _ => Err(ValidationError::IncompatibleKey(alg)),
+    }
+}
+
+pub(crate) fn verify(alg: Algorithm, key: &VerifyingKey, message: &[u8], signature: &[u8]) -> Result<bool, ValidationError> {
+    match alg {
+        Algorithm::EdDSA => Ok(Signature::from_slice(signature).is_ok_and(|sig| key.verify_strict(message, &sig).is_ok())),
+        _ => Err(ValidationError::IncompatibleKey(alg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use chrono::{TimeZone as _, Utc};
+
+    use super::*;
+    use crate::{
+        jws::{self, tests::pem_to_der, Header, TokenData},
+        RegisteredClaims, Validation,
+    };
+
+    // https://datatracker.ietf.org/doc/html/rfc8037#appendix-A.4
+    const RFC8037_A4_TOKEN: &str = "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+    const RFC8037_A1_X: &str = "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo";
+    const PRIVATE_PEM: &str = include_str!("../../testdata/rfc8037_a1_private.pem");
+    const PUBLIC_PEM: &str = include_str!("../../testdata/rfc8037_a1_public.pem");
+
+    #[test]
+    fn matches_rfc8037_example() {
+        let (signing_input, signature) = RFC8037_A4_TOKEN.rsplit_once('.').unwrap();
+        let signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+
+        let key = signing_key_from_pem(PRIVATE_PEM).unwrap();
+        assert_eq!(sign(Algorithm::EdDSA, &key, signing_input.as_bytes()).unwrap(), signature);
+
+        for key in [
+            verifying_key_from_pem(PUBLIC_PEM).unwrap(),
+            verifying_key_from_der(&pem_to_der(PUBLIC_PEM)).unwrap(),
+            verifying_key_from_bytes(&URL_SAFE_NO_PAD.decode(RFC8037_A1_X).unwrap()).unwrap(),
+        ] {
+            assert!(verify(Algorithm::EdDSA, &key, signing_input.as_bytes(), &signature).unwrap());
+            assert!(!verify(Algorithm::EdDSA, &key, b"tampered", &signature).unwrap());
+        }
+    }
+
+    #[test]
+    fn round_trips_claims() {
+        let claims = RegisteredClaims {
+            issuer: "mesh".to_string(),
+            expires_at: Utc.timestamp_opt(Utc::now().timestamp() + 300, 0).single(),
+            ..Default::default()
+        };
+        let signing_key = jws::SigningKey::from_ed25519_der(&pem_to_der(PRIVATE_PEM)).unwrap();
+        let token = jws::sign(&Header::new(Algorithm::EdDSA), &claims, &signing_key).unwrap();
+        let verifying_key = jws::VerifyingKey::from_ed25519_pem(PUBLIC_PEM).unwrap();
+        let verified: TokenData<RegisteredClaims> = jws::verify(&token, &verifying_key, &Validation::new()).unwrap();
+        assert_eq!(verified.claims, claims);
+
+        let err = jws::sign(&Header::new(Algorithm::ES256), &claims, &signing_key).unwrap_err();
+        assert_eq!(err, ValidationError::IncompatibleKey(Algorithm::ES256));
+        assert!(jws::VerifyingKey::from_ed25519_bytes(&[0u8; 31]).is_err());
+    }
+}
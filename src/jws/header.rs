@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::Algorithm;
+use crate::ValidationError;
+
+// Header Parameter names defined by RFC 7515 and RFC 7518, which must not be listed in `crit`.
+// See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.11
+const REGISTERED_NAMES: &[&str] = &["alg", "jku", "jwk", "kid", "x5u", "x5c", "x5t", "x5t#S256", "typ", "cty", "crit"];
+
+// Header is the JOSE Header of a JWS. See https://datatracker.ietf.org/doc/html/rfc7515#section-4
+//
+// The registered Header Parameters are typed fields; any other parameter is kept in `extensions`
+// so that it survives a round trip and can be listed in `crit`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    // the `alg` (Algorithm) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.1
+    #[serde(rename = "alg")]
+    pub algorithm: Algorithm,
+
+    // the `jku` (JWK Set URL) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.2
+    #[serde(rename = "jku", default, skip_serializing_if = "Option::is_none")]
+    pub jwk_set_url: Option<String>,
+
+    // the `kid` (Key ID) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.4
+    #[serde(rename = "kid", default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+
+    // the `x5u` (X.509 URL) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.5
+    #[serde(rename = "x5u", default, skip_serializing_if = "Option::is_none")]
+    pub x509_url: Option<String>,
+
+    // the `x5c` (X.509 Certificate Chain) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.6
+    #[serde(rename = "x5c", default, skip_serializing_if = "Option::is_none")]
+    pub x509_chain: Option<Vec<String>>,
+
+    // the `x5t` (X.509 Certificate SHA-1 Thumbprint) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.7
+    #[serde(rename = "x5t", default, skip_serializing_if = "Option::is_none")]
+    pub x509_sha1_thumbprint: Option<String>,
+
+    // the `x5t#S256` (X.509 Certificate SHA-256 Thumbprint) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.8
+    #[serde(rename = "x5t#S256", default, skip_serializing_if = "Option::is_none")]
+    pub x509_sha256_thumbprint: Option<String>,
+
+    // the `typ` (Type) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.9
+    #[serde(rename = "typ", default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+
+    // the `cty` (Content Type) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.10
+    #[serde(rename = "cty", default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    // the `crit` (Critical) Header Parameter. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.11
+    #[serde(rename = "crit", default, skip_serializing_if = "Option::is_none")]
+    pub critical: Option<Vec<String>>,
+
+    // every other Header Parameter, keyed by name.
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Header {
+    // Creates a header for `algorithm` with `typ` set to `JWT`.
+    pub fn new(algorithm: Algorithm) -> Self {
+        Header {
+            algorithm,
+            jwk_set_url: None,
+            key_id: None,
+            x509_url: None,
+            x509_chain: None,
+            x509_sha1_thumbprint: None,
+            x509_sha256_thumbprint: None,
+            token_type: Some("JWT".to_string()),
+            content_type: None,
+            critical: None,
+            extensions: Map::new(),
+        }
+    }
+
+    // Reports whether the `typ` parameter names `expected`. Media types compare case-insensitively,
+    // and an `application/` prefix is optional. See https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.9
+    pub fn has_type(&self, expected: &str) -> bool {
+        type_matches(self.token_type.as_deref(), expected)
+    }
+
+    // Applies the `crit` rules: the list must be non-empty, must only name extension parameters that are
+    // present in the header, and every name must be in `understood`.
+    pub fn verify_critical(&self, understood: &[String]) -> Result<(), ValidationError> {
+        verify_critical(self.critical.as_deref(), &self.extensions, REGISTERED_NAMES, understood)
+    }
+}
+
+// Compares a `typ` Header Parameter with `expected`, as described on `Header::has_type`.
+pub(crate) fn type_matches(typ: Option<&str>, expected: &str) -> bool {
+    fn normalize(typ: &str) -> String {
+        let typ = typ.to_ascii_lowercase();
+        match typ.strip_prefix("application/") {
+            Some(subtype) if !subtype.contains('/') => subtype.to_string(),
+            _ => typ,
+        }
+    }
+    typ.is_some_and(|typ| normalize(typ) == normalize(expected))
+}
+
+// Applies the `crit` rules, as described on `Header::verify_critical`, given the Header Parameter names registered
+// for the kind of JOSE Header being checked.
+pub(crate) fn verify_critical(
+    critical: Option<&[String]>,
+    extensions: &Map<String, Value>,
+    registered: &[&str],
+    understood: &[String],
+) -> Result<(), ValidationError> {
+    let Some(critical) = critical else {
+        return Ok(());
+    };
+    if critical.is_empty() {
+        return Err(ValidationError::MalformedToken("`crit` must not be empty".to_string()));
+    }
+    for name in critical {
+        if registered.contains(&name.as_str()) {
+            return Err(ValidationError::MalformedToken(format!(
+                "`crit` must not list registered parameter `{name}`"
+            )));
+        }
+        if !extensions.contains_key(name) {
+            return Err(ValidationError::MalformedToken(format!("`crit` lists missing parameter `{name}`")));
+        }
+        if !understood.contains(name) {
+            return Err(ValidationError::UnsupportedCriticalHeader(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_registered_and_extension_parameters() {
+        let input = r#"{"alg":"ES256","kid":"2024-01","typ":"at+jwt","cty":"JWT","crit":["exp"],"exp":1363284000,"x5t#S256":"abc"}"#;
+        let header: Header = serde_json::from_str(input).unwrap();
+        assert_eq!(header.algorithm, Algorithm::ES256);
+        assert_eq!(header.key_id.as_deref(), Some("2024-01"));
+        assert_eq!(header.content_type.as_deref(), Some("JWT"));
+        assert_eq!(header.x509_sha256_thumbprint.as_deref(), Some("abc"));
+        assert_eq!(header.extensions["exp"], 1363284000);
+        assert!(header.has_type("application/AT+JWT"));
+        assert!(!header.has_type("JWT"));
+
+        let output: Value = serde_json::to_value(&header).unwrap();
+        assert_eq!(output, serde_json::from_str::<Value>(input).unwrap());
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.11
+    #[test]
+    fn enforces_critical_parameters() {
+        let header: Header = serde_json::from_str(r#"{"alg":"ES256","crit":["exp"],"exp":1363284000}"#).unwrap();
+        assert_eq!(
+            header.verify_critical(&[]),
+            Err(ValidationError::UnsupportedCriticalHeader("exp".to_string()))
+        );
+        assert_eq!(header.verify_critical(&["exp".to_string()]), Ok(()));
+
+        for input in [
+            r#"{"alg":"ES256","crit":[]}"#,
+            r#"{"alg":"ES256","crit":["kid"],"kid":"1"}"#,
+            r#"{"alg":"ES256","crit":["exp"]}"#,
+        ] {
+            let header: Header = serde_json::from_str(input).unwrap();
+            let err = header.verify_critical(&["exp".to_string(), "kid".to_string()]).unwrap_err();
+            assert_eq!(err.reason(), "malformed_token", "{input}");
+        }
+    }
+}
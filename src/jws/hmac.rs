@@ -0,0 +1,42 @@
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+use subtle::ConstantTimeEq;
+
+use super::Algorithm;
+use crate::ValidationError;
+
+// Computes the MAC of `message`. RFC 7518 requires a key at least as long as the hash output,
+// which is enforced when signing but not when verifying tokens issued elsewhere.
+// See https://datatracker.ietf.org/doc/html/rfc7518#section-3.2
+pub(crate) fn sign(alg: Algorithm, secret: &[u8], message: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    let min_len = match alg {
+        Algorithm::HS256 => 32,
+        Algorithm::HS384 => 48,
+        Algorithm::HS512 => 64,
+        _ => return Err(ValidationError::IncompatibleKey(alg)),
+    };
+    if secret.len() < min_len {
+        return Err(ValidationError::InvalidKey(format!("{alg} requires a key of at least {min_len} bytes")));
+    }
+    mac(alg, secret, message)
+}
+
+// Recomputes the MAC of `message` and compares it with `signature` in constant time.
+pub(crate) fn verify(alg: Algorithm, secret: &[u8], message: &[u8], signature: &[u8]) -> Result<bool, ValidationError> {
+    let expected = mac(alg, secret, message)?;
+    Ok(expected.ct_eq(signature).unwrap_u8() == 1)
+}
+
+fn mac(alg: Algorithm, secret: &[u8], message: &[u8]) -> Result<Vec<u8>, ValidationError> {
+    fn compute<M: Mac + hmac::digest::KeyInit>(secret: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+        mac.update(message);
+        mac.finalize().into_bytes().to_vec()
+    }
+    match alg {
+        Algorithm::HS256 => Ok(compute::<Hmac<Sha256>>(secret, message)),
+        Algorithm::HS384 => Ok(compute::<Hmac<Sha384>>(secret, message)),
+        Algorithm::HS512 => Ok(compute::<Hmac<Sha512>>(secret, message)),
+        _ => Err(ValidationError::IncompatibleKey(alg)),
+    }
+}
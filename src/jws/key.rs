@@ -0,0 +1,273 @@
+use std::fmt;
+
+use super::Algorithm;
+use crate::ValidationError;
+
+// SigningKey is the private or secret key used to sign a token.
+#[derive(Clone)]
+pub struct SigningKey {
+    inner: SigningKeyInner,
+}
+
+#[derive(Clone)]
+enum SigningKeyInner {
+    #[cfg(feature = "jws-hmac")]
+    Hmac(Vec<u8>),
+    #[cfg(feature = "jws-rsa")]
+    Rsa(Box<::rsa::RsaPrivateKey>),
+    #[cfg(feature = "jws-ecdsa")]
+    Ec(Box<super::ecdsa::EcSigningKey>),
+    #[cfg(feature = "jws-eddsa")]
+    Ed25519(Box<ed25519_dalek::SigningKey>),
+}
+
+// VerifyingKey is the public or secret key used to verify a token's signature.
+//
+// Each key carries the algorithms it accepts, so a token can never pick its own: by default that is every
+// algorithm of the key's family (HS* for secrets, RS* and PS* for RSA, the ES* algorithm matching an EC
+// key's curve, EdDSA for Ed25519), and `with_algorithms` narrows it further.
+#[derive(Clone)]
+pub struct VerifyingKey {
+    inner: VerifyingKeyInner,
+    algorithms: Vec<Algorithm>,
+}
+
+#[derive(Clone)]
+enum VerifyingKeyInner {
+    #[cfg(feature = "jws-hmac")]
+    Hmac(Vec<u8>),
+    #[cfg(feature = "jws-rsa")]
+    Rsa(Box<::rsa::RsaPublicKey>),
+    #[cfg(feature = "jws-ecdsa")]
+    Ec(Box<super::ecdsa::EcVerifyingKey>),
+    #[cfg(feature = "jws-eddsa")]
+    Ed25519(Box<ed25519_dalek::VerifyingKey>),
+}
+
+impl SigningKey {
+    // Creates a key for the HS256, HS384 and HS512 algorithms from a shared secret.
+    #[cfg(feature = "jws-hmac")]
+    pub fn from_hmac_secret(secret: &[u8]) -> Self {
+        SigningKey {
+            inner: SigningKeyInner::Hmac(secret.to_vec()),
+        }
+    }
+
+    // Loads an RSA private key for the RS* and PS* algorithms from a PKCS#8 or PKCS#1 PEM document.
+    #[cfg(feature = "jws-rsa")]
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(SigningKey {
+            inner: SigningKeyInner::Rsa(Box::new(super::rsa::private_key_from_pem(pem)?)),
+        })
+    }
+
+    // Loads an RSA private key for the RS* and PS* algorithms from a PKCS#8 or PKCS#1 DER document.
+    #[cfg(feature = "jws-rsa")]
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(SigningKey {
+            inner: SigningKeyInner::Rsa(Box::new(super::rsa::private_key_from_der(der)?)),
+        })
+    }
+
+    // Loads an ECDSA private key for the ES* algorithms from a PKCS#8 or SEC1 PEM document.
+    #[cfg(feature = "jws-ecdsa")]
+    pub fn from_ec_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(SigningKey {
+            inner: SigningKeyInner::Ec(Box::new(super::ecdsa::EcSigningKey::from_pem(pem)?)),
+        })
+    }
+
+    // Loads an ECDSA private key for the ES* algorithms from a PKCS#8 or SEC1 DER document.
+    #[cfg(feature = "jws-ecdsa")]
+    pub fn from_ec_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(SigningKey {
+            inner: SigningKeyInner::Ec(Box::new(super::ecdsa::EcSigningKey::from_der(der)?)),
+        })
+    }
+
+    // Loads an Ed25519 private key for the EdDSA algorithm from a PKCS#8 PEM document.
+    #[cfg(feature = "jws-eddsa")]
+    pub fn from_ed25519_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(SigningKey {
+            inner: SigningKeyInner::Ed25519(Box::new(super::eddsa::signing_key_from_pem(pem)?)),
+        })
+    }
+
+    // Loads an Ed25519 private key for the EdDSA algorithm from a PKCS#8 DER document.
+    #[cfg(feature = "jws-eddsa")]
+    pub fn from_ed25519_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(SigningKey {
+            inner: SigningKeyInner::Ed25519(Box::new(super::eddsa::signing_key_from_der(der)?)),
+        })
+    }
+
+    // Without an algorithm feature the key types are uninhabited, and so is this match.
+    #[cfg_attr(
+        not(any(feature = "jws-hmac", feature = "jws-rsa", feature = "jws-ecdsa", feature = "jws-eddsa")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn sign(&self, alg: Algorithm, message: &[u8]) -> Result<Vec<u8>, ValidationError> {
+        match self.inner {
+            #[cfg(feature = "jws-hmac")]
+            SigningKeyInner::Hmac(ref secret) => super::hmac::sign(alg, secret, message),
+            #[cfg(feature = "jws-rsa")]
+            SigningKeyInner::Rsa(ref key) => super::rsa::sign(alg, key, message),
+            #[cfg(feature = "jws-ecdsa")]
+            SigningKeyInner::Ec(ref key) => key.sign(alg, message),
+            #[cfg(feature = "jws-eddsa")]
+            SigningKeyInner::Ed25519(ref key) => super::eddsa::sign(alg, key, message),
+        }
+    }
+}
+
+impl VerifyingKey {
+    // Creates a key for the HS256, HS384 and HS512 algorithms from a shared secret.
+    #[cfg(feature = "jws-hmac")]
+    pub fn from_hmac_secret(secret: &[u8]) -> Self {
+        VerifyingKey::new(VerifyingKeyInner::Hmac(secret.to_vec()))
+    }
+
+    // Loads an RSA public key for the RS* and PS* algorithms from a SubjectPublicKeyInfo or PKCS#1 PEM document.
+    #[cfg(feature = "jws-rsa")]
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Rsa(Box::new(super::rsa::public_key_from_pem(pem)?))))
+    }
+
+    // Loads an RSA public key for the RS* and PS* algorithms from a SubjectPublicKeyInfo or PKCS#1 DER document.
+    #[cfg(feature = "jws-rsa")]
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Rsa(Box::new(super::rsa::public_key_from_der(der)?))))
+    }
+
+    // Builds an RSA public key from its big-endian modulus `n` and public exponent `e`.
+    #[cfg(feature = "jws-rsa")]
+    pub fn from_rsa_components(n: &[u8], e: &[u8]) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Rsa(Box::new(
+            super::rsa::public_key_from_components(n, e)?,
+        ))))
+    }
+
+    // Loads an ECDSA public key for the ES* algorithms from a SubjectPublicKeyInfo PEM document.
+    #[cfg(feature = "jws-ecdsa")]
+    pub fn from_ec_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Ec(Box::new(
+            super::ecdsa::EcVerifyingKey::from_pem(pem)?,
+        ))))
+    }
+
+    // Loads an ECDSA public key for the ES* algorithms from a SubjectPublicKeyInfo DER document.
+    #[cfg(feature = "jws-ecdsa")]
+    pub fn from_ec_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Ec(Box::new(
+            super::ecdsa::EcVerifyingKey::from_der(der)?,
+        ))))
+    }
+
+    // Builds an ECDSA public key from its big-endian affine coordinates `x` and `y`.
+    #[cfg(feature = "jws-ecdsa")]
+    pub fn from_ec_components(x: &[u8], y: &[u8]) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Ec(Box::new(
+            super::ecdsa::EcVerifyingKey::from_components(x, y)?,
+        ))))
+    }
+
+    // Loads an Ed25519 public key for the EdDSA algorithm from a SubjectPublicKeyInfo PEM document.
+    #[cfg(feature = "jws-eddsa")]
+    pub fn from_ed25519_pem(pem: &str) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Ed25519(Box::new(
+            super::eddsa::verifying_key_from_pem(pem)?,
+        ))))
+    }
+
+    // Loads an Ed25519 public key for the EdDSA algorithm from a SubjectPublicKeyInfo DER document.
+    #[cfg(feature = "jws-eddsa")]
+    pub fn from_ed25519_der(der: &[u8]) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Ed25519(Box::new(
+            super::eddsa::verifying_key_from_der(der)?,
+        ))))
+    }
+
+    // Builds an Ed25519 public key from its 32 raw bytes.
+    #[cfg(feature = "jws-eddsa")]
+    pub fn from_ed25519_bytes(bytes: &[u8]) -> Result<Self, ValidationError> {
+        Ok(VerifyingKey::new(VerifyingKeyInner::Ed25519(Box::new(
+            super::eddsa::verifying_key_from_bytes(bytes)?,
+        ))))
+    }
+
+    #[cfg_attr(
+        not(any(feature = "jws-hmac", feature = "jws-rsa", feature = "jws-ecdsa", feature = "jws-eddsa")),
+        allow(dead_code, unused_variables, unreachable_code)
+    )]
+    fn new(inner: VerifyingKeyInner) -> Self {
+        let algorithms = match inner {
+            #[cfg(feature = "jws-hmac")]
+            VerifyingKeyInner::Hmac(_) => vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+            #[cfg(feature = "jws-rsa")]
+            VerifyingKeyInner::Rsa(_) => vec![
+                Algorithm::RS256,
+                Algorithm::RS384,
+                Algorithm::RS512,
+                Algorithm::PS256,
+                Algorithm::PS384,
+                Algorithm::PS512,
+            ],
+            #[cfg(feature = "jws-ecdsa")]
+            VerifyingKeyInner::Ec(ref key) => vec![key.algorithm()],
+            #[cfg(feature = "jws-eddsa")]
+            VerifyingKeyInner::Ed25519(_) => vec![Algorithm::EdDSA],
+        };
+        VerifyingKey { inner, algorithms }
+    }
+
+    // Restricts the key to `algorithms`. Tokens signed with any other algorithm are rejected with
+    // `ValidationError::AlgorithmNotAllowed`, even if the key could verify them.
+    pub fn with_algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
+    // Returns the algorithms this key accepts.
+    pub fn algorithms(&self) -> &[Algorithm] {
+        &self.algorithms
+    }
+
+    // Reports whether this key accepts tokens signed with `alg`.
+    pub fn allows(&self, alg: Algorithm) -> bool {
+        self.algorithms.contains(&alg)
+    }
+
+    #[cfg_attr(
+        not(any(feature = "jws-hmac", feature = "jws-rsa", feature = "jws-ecdsa", feature = "jws-eddsa")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn verify(&self, alg: Algorithm, message: &[u8], signature: &[u8]) -> Result<bool, ValidationError> {
+        if !self.allows(alg) {
+            return Err(ValidationError::AlgorithmNotAllowed(alg));
+        }
+        match self.inner {
+            #[cfg(feature = "jws-hmac")]
+            VerifyingKeyInner::Hmac(ref secret) => super::hmac::verify(alg, secret, message, signature),
+            #[cfg(feature = "jws-rsa")]
+            VerifyingKeyInner::Rsa(ref key) => super::rsa::verify(alg, key, message, signature),
+            #[cfg(feature = "jws-ecdsa")]
+            VerifyingKeyInner::Ec(ref key) => key.verify(alg, message, signature),
+            #[cfg(feature = "jws-eddsa")]
+            VerifyingKeyInner::Ed25519(ref key) => super::eddsa::verify(alg, key, message, signature),
+        }
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerifyingKey")
+            .field("algorithms", &self.algorithms)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,137 @@
+//! JSON Web Signature support for claim sets, as referenced at https://datatracker.ietf.org/doc/html/rfc7515.
+//!
+//! Only the JWS Compact Serialization is supported. The signature algorithms themselves are behind
+//! cargo features: `jws-hmac` enables HS256, HS384 and HS512, `jws-rsa` enables RS256, RS384,
+//! RS512, PS256, PS384 and PS512, `jws-ecdsa` enables ES256, ES384 and ES512, and `jws-eddsa`
+//! enables EdDSA over Ed25519.
+//!
+//! A token never chooses its own algorithm: each `VerifyingKey` only accepts the algorithms of its own
+//! family, optionally narrowed with `VerifyingKey::with_algorithms`, and Unsecured JWSs using `none`
+//! are always rejected.
+//!
+//! Keys published as a JSON Web Key Set can be parsed into a `JwkSet`, which picks the verification
+//! key for a token by its `kid` Header Parameter. `CachedJwks` keeps such a set loaded from a file or
+//! fetched from an identity provider, reloading it as keys rotate.
+//!
+//! When the key set or policy depends on the token itself, `decode_unverified` reads its header and claims
+//! without verifying them, for routing only.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "jws")]
+pub(crate) use self::compact::verify_with;
+pub(crate) use self::header::type_matches;
+#[cfg(feature = "jwe")]
+pub(crate) use self::header::verify_critical;
+#[cfg(feature = "jws")]
+pub use self::{
+    compact::{sign, verify, verify_at, TokenData},
+    key::{SigningKey, VerifyingKey},
+    provider::{CachedJwks, KeyProvider},
+    unverified::{decode_unverified, UnverifiedToken},
+};
+pub use self::{
+    header::Header,
+    jwk::{Jwk, JwkSet, KeyParams},
+};
+
+#[cfg(feature = "jws")]
+mod compact;
+#[cfg(feature = "jws-ecdsa")]
+mod ecdsa;
+#[cfg(feature = "jws-eddsa")]
+mod eddsa;
+mod header;
+#[cfg(feature = "jws-hmac")]
+mod hmac;
+mod jwk;
+#[cfg(feature = "jws")]
+mod key;
+#[cfg(feature = "jws")]
+mod provider;
+#[cfg(feature = "jws-rsa")]
+mod rsa;
+#[cfg(feature = "jws")]
+mod unverified;
+
+// Algorithm is a JWS digital signature or MAC algorithm. See https://datatracker.ietf.org/doc/html/rfc7518#section-3.1
+#[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    HS256,
+    HS384,
+    HS512,
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES384,
+    ES512,
+    EdDSA,
+}
+
+impl Algorithm {
+    // Returns the `alg` Header Parameter value, e.g. `HS256`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::HS256 => "HS256",
+            Algorithm::HS384 => "HS384",
+            Algorithm::HS512 => "HS512",
+            Algorithm::RS256 => "RS256",
+            Algorithm::RS384 => "RS384",
+            Algorithm::RS512 => "RS512",
+            Algorithm::PS256 => "PS256",
+            Algorithm::PS384 => "PS384",
+            Algorithm::PS512 => "PS512",
+            Algorithm::ES256 => "ES256",
+            Algorithm::ES384 => "ES384",
+            Algorithm::ES512 => "ES512",
+            Algorithm::EdDSA => "EdDSA",
+        }
+    }
+
+    // Looks up an algorithm by its `alg` Header Parameter value. Returns `None` for unknown names and for `none`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Algorithm::HS256,
+            Algorithm::HS384,
+            Algorithm::HS512,
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+            Algorithm::ES256,
+            Algorithm::ES384,
+            Algorithm::ES512,
+            Algorithm::EdDSA,
+        ]
+        .into_iter()
+        .find(|alg| alg.name() == name)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(all(test, any(feature = "jws-rsa", feature = "jws-ecdsa", feature = "jws-eddsa")))]
+pub(crate) mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    // Strips the PEM armor from a test key, leaving the DER document.
+    pub(crate) fn pem_to_der(pem: &str) -> Vec<u8> {
+        STANDARD
+            .decode(pem.lines().filter(|l| !l.starts_with("-----")).collect::<String>())
+            .unwrap()
+    }
+}
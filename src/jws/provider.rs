@@ -0,0 +1,477 @@
+use std::{
+    borrow::Cow,
+    error::Error,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError, RwLock, TryLockError,
+    },
+    thread,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+
+use super::{Header, JwkSet, TokenData, VerifyingKey};
+use crate::{Clock, RegisteredClaims, SystemClock, Validation, ValidationError};
+
+// KeyProvider supplies the key that verifies a token, chosen from its JOSE Header.
+//
+// `JwkSet` is the static implementation; `CachedJwks` keeps a key set fetched from elsewhere up to date.
+pub trait KeyProvider: Send + Sync {
+    // Returns the key to verify a token with `header`.
+    fn verifying_key(&self, header: &Header) -> Result<VerifyingKey, ValidationError>;
+
+    // Verifies a JWS Compact Serialization with the key its header selects, then validates its header and claims against `validation`.
+    fn verify<C>(&self, token: &str, validation: &Validation) -> Result<TokenData<C>, ValidationError>
+    where
+        Self: Sized,
+        C: DeserializeOwned + AsRef<RegisteredClaims>,
+    {
+        self.verify_at(token, validation, &SystemClock)
+    }
+
+    // Like `verify`, but reads the current time from `clock`.
+    fn verify_at<C>(&self, token: &str, validation: &Validation, clock: &dyn Clock) -> Result<TokenData<C>, ValidationError>
+    where
+        Self: Sized,
+        C: DeserializeOwned + AsRef<RegisteredClaims>,
+    {
+        super::verify_with(token, validation, clock, |header| Ok(Cow::Owned(self.verifying_key(header)?)))
+    }
+}
+
+impl<P: KeyProvider + ?Sized> KeyProvider for &P {
+    fn verifying_key(&self, header: &Header) -> Result<VerifyingKey, ValidationError> {
+        (**self).verifying_key(header)
+    }
+}
+
+impl<P: KeyProvider + ?Sized> KeyProvider for Box<P> {
+    fn verifying_key(&self, header: &Header) -> Result<VerifyingKey, ValidationError> {
+        (**self).verifying_key(header)
+    }
+}
+
+impl<P: KeyProvider + ?Sized> KeyProvider for Arc<P> {
+    fn verifying_key(&self, header: &Header) -> Result<VerifyingKey, ValidationError> {
+        (**self).verifying_key(header)
+    }
+}
+
+// A single key verifies every token, whatever its `kid`.
+impl KeyProvider for VerifyingKey {
+    fn verifying_key(&self, _header: &Header) -> Result<VerifyingKey, ValidationError> {
+        Ok(self.clone())
+    }
+}
+
+type Fetch = dyn Fn() -> Result<JwkSet, Box<dyn Error + Send + Sync>> + Send + Sync;
+
+// CachedJwks is a `KeyProvider` that caches a JWK Set loaded from a file or a user-supplied fetch function.
+//
+// The set is loaded on first use and reloaded once `refresh_interval` has passed. A token naming an unknown
+// `kid` also triggers a reload, so rotated keys are picked up without waiting, but reloads never happen more
+// often than `min_refresh_interval`. If reloading keeps failing, the cached keys are still used until they
+// are older than `max_age`, after which every lookup fails with `ValidationError::KeySetUnavailable`.
+//
+// Reloads run on the thread of the lookup that finds them due, one at a time. While one runs, other lookups keep
+// using the cached set rather than waiting for it; only the first load, and lookups for an unknown `kid`, wait.
+// `spawn_refresh` moves the scheduled reloads to a thread of their own instead.
+pub struct CachedJwks {
+    fetch: Box<Fetch>,
+    clock: Box<dyn Clock>,
+    // how long a loaded set is used before it is reloaded.
+    pub refresh_interval: Duration,
+    // the minimum time between two reloads, which bounds the load caused by tokens with unknown `kid`s.
+    pub min_refresh_interval: Duration,
+    // how long a loaded set may be used while reloading fails.
+    pub max_age: Duration,
+    state: RwLock<State>,
+    // serializes reloads so concurrent lookups do not fetch the same set several times.
+    refreshing: Mutex<()>,
+    // set once `spawn_refresh` has started a thread that reloads on schedule.
+    background: AtomicBool,
+}
+
+#[derive(Default)]
+struct State {
+    keys: Option<Arc<JwkSet>>,
+    loaded_at: Option<DateTime<Utc>>,
+    attempted_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+impl CachedJwks {
+    // Creates a cache that loads the set by calling `fetch`, e.g. to download it from an identity provider's `jwks_uri`.
+    //
+    // `fetch` is called synchronously from the verifying thread, so it must not block for long: in an async
+    // server a lookup runs on the executor, which a slow download would stall. Use a client with a short timeout,
+    // or `spawn_refresh` so that lookups only fetch for an unknown `kid`.
+    pub fn from_fetch<F>(fetch: F) -> Self
+    where
+        F: Fn() -> Result<JwkSet, Box<dyn Error + Send + Sync>> + Send + Sync + 'static,
+    {
+        CachedJwks {
+            fetch: Box::new(fetch),
+            clock: Box::new(SystemClock),
+            refresh_interval: Duration::hours(1),
+            min_refresh_interval: Duration::seconds(30),
+            max_age: Duration::hours(24),
+            state: RwLock::new(State::default()),
+            refreshing: Mutex::new(()),
+            background: AtomicBool::new(false),
+        }
+    }
+
+    // Creates a cache that loads the set from a JSON file at `path`.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self::from_fetch(move || Ok(serde_json::from_slice(&std::fs::read(&path)?)?))
+    }
+
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    pub fn with_min_refresh_interval(mut self, interval: Duration) -> Self {
+        self.min_refresh_interval = interval;
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    // Reads the current time from `clock` when deciding whether to reload.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    // Reloads the set now, regardless of `min_refresh_interval`.
+    pub fn refresh(&self) -> Result<(), ValidationError> {
+        self.refresh_at(self.clock.now(), true, true)
+    }
+
+    // Starts a thread that loads the set, then reloads it every `refresh_interval`, or every `min_refresh_interval`
+    // while reloading fails, but never more often than every `min_refresh_interval`, or every second if that is not
+    // positive. Lookups then no longer reload on schedule themselves. The thread stops when it wakes up after the
+    // cache has been dropped.
+    pub fn spawn_refresh(self: &Arc<Self>) -> thread::JoinHandle<()> {
+        self.background.store(true, Ordering::Relaxed);
+        let cache = Arc::downgrade(self);
+        thread::spawn(move || loop {
+            let Some(cache) = cache.upgrade() else {
+                return;
+            };
+            let interval = match cache.refresh() {
+                Ok(()) => cache.refresh_interval,
+                Err(_) => cache.min_refresh_interval,
+            };
+            let interval = interval
+                .max(cache.min_refresh_interval)
+                .to_std()
+                .ok()
+                .filter(|interval| !interval.is_zero());
+            drop(cache);
+            thread::sleep(interval.unwrap_or(std::time::Duration::from_secs(1)));
+        })
+    }
+
+    // Returns the cached set, reloading it first if it is due.
+    pub fn keys(&self) -> Result<Arc<JwkSet>, ValidationError> {
+        let now = self.clock.now();
+        let (due, usable) = {
+            let state = self.read();
+            (
+                state.loaded_at.is_none_or(|at| now - at >= self.refresh_interval),
+                self.usable(&state, now).is_some(),
+            )
+        };
+        if due && !(usable && self.background.load(Ordering::Relaxed)) {
+            // A failed reload is reported below, once the cached keys are too old to fall back on. With usable
+            // keys, a reload started by another lookup is not waited for.
+            let _ = self.refresh_at(now, false, !usable);
+        }
+        let state = self.read();
+        self.usable(&state, now)
+            .ok_or_else(|| ValidationError::KeySetUnavailable(state.last_error.clone().unwrap_or_else(|| "no key set loaded".to_string())))
+    }
+
+    // Returns the cached set, unless there is none or it is older than `max_age`.
+    fn usable(&self, state: &State, now: DateTime<Utc>) -> Option<Arc<JwkSet>> {
+        match (state.keys.as_ref(), state.loaded_at) {
+            (Some(keys), Some(at)) if now - at < self.max_age => Some(keys.clone()),
+            _ => None,
+        }
+    }
+
+    // Reloads the set unless `force` is false and it was attempted within `min_refresh_interval`. If another
+    // reload is running, this waits for it and then decides, or returns at once when `wait` is false.
+    fn refresh_at(&self, now: DateTime<Utc>, force: bool, wait: bool) -> Result<(), ValidationError> {
+        let _refreshing = match self.refreshing.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) if wait => self.refreshing.lock().unwrap_or_else(PoisonError::into_inner),
+            Err(TryLockError::WouldBlock) => return Ok(()),
+        };
+        if !force && self.read().attempted_at.is_some_and(|at| now - at < self.min_refresh_interval) {
+            return Ok(());
+        }
+        self.write().attempted_at = Some(now);
+        let result = (self.fetch)();
+        let mut state = self.write();
+        match result {
+            Ok(keys) => {
+                state.keys = Some(Arc::new(keys));
+                state.loaded_at = Some(now);
+                state.last_error = None;
+                Ok(())
+            }
+            Err(e) => {
+                state.last_error = Some(e.to_string());
+                Err(ValidationError::KeySetUnavailable(e.to_string()))
+            }
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, State> {
+        self.state.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl KeyProvider for CachedJwks {
+    fn verifying_key(&self, header: &Header) -> Result<VerifyingKey, ValidationError> {
+        let keys = self.keys()?;
+        match keys.select(header) {
+            Err(ValidationError::UnknownKeyId(kid)) => {
+                // The issuer may have rotated its keys since the set was loaded.
+                if self.refresh_at(self.clock.now(), false, true).is_err() {
+                    return Err(ValidationError::UnknownKeyId(kid));
+                }
+                self.keys()?.select(header)?.to_verifying_key()
+            }
+            selected => selected?.to_verifying_key(),
+        }
+    }
+}
+
+impl std::fmt::Debug for CachedJwks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedJwks")
+            .field("refresh_interval", &self.refresh_interval)
+            .field("min_refresh_interval", &self.min_refresh_interval)
+            .field("max_age", &self.max_age)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "jws-hmac"))]
+mod tests {
+    use std::sync::{atomic::AtomicUsize, mpsc};
+
+    use chrono::TimeZone as _;
+
+    use super::*;
+    use crate::jws::{sign, Algorithm, SigningKey};
+
+    // A clock the test can move forward while the cache holds on to it.
+    #[derive(Default)]
+    struct ManualClock(Mutex<i64>);
+
+    impl ManualClock {
+        fn advance(&self, secs: i64) {
+            *self.0.lock().unwrap() += secs;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            Utc.timestamp_opt(1_700_000_000 + *self.0.lock().unwrap(), 0).unwrap()
+        }
+    }
+
+    fn oct_set(kids: &[&str]) -> JwkSet {
+        let keys: Vec<_> = kids
+            .iter()
+            .map(|kid| {
+                serde_json::json!({
+                    "kty": "oct",
+                    "kid": kid,
+                    "k": base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, secret(kid)),
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({ "keys": keys })).unwrap()
+    }
+
+    fn secret(kid: &str) -> Vec<u8> {
+        kid.bytes().cycle().take(32).collect()
+    }
+
+    fn token(kid: &str) -> String {
+        let mut header = Header::new(Algorithm::HS256);
+        header.key_id = Some(kid.to_string());
+        sign(&header, &RegisteredClaims::default(), &SigningKey::from_hmac_secret(&secret(kid))).unwrap()
+    }
+
+    fn validation() -> Validation {
+        Validation::new().with_required_claims([])
+    }
+
+    // Builds a cache over a set the test can replace, counting how often it is fetched.
+    fn cache(upstream: &Arc<Mutex<Result<JwkSet, String>>>, fetches: &Arc<AtomicUsize>, clock: &Arc<ManualClock>) -> CachedJwks {
+        let (upstream, fetches) = (upstream.clone(), fetches.clone());
+        CachedJwks::from_fetch(move || {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(upstream.lock().unwrap().clone()?)
+        })
+        .with_refresh_interval(Duration::minutes(10))
+        .with_min_refresh_interval(Duration::seconds(30))
+        .with_max_age(Duration::hours(1))
+        .with_clock(clock.clone())
+    }
+
+    #[test]
+    fn refreshes_on_interval_and_unknown_kid() {
+        let upstream = Arc::new(Mutex::new(Ok(oct_set(&["2024-01"]))));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let clock = Arc::new(ManualClock::default());
+        let jwks = cache(&upstream, &fetches, &clock);
+
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // The issuer rotates; the first token with the new `kid` reloads the set.
+        *upstream.lock().unwrap() = Ok(oct_set(&["2024-01", "2024-02"]));
+        clock.advance(60);
+        jwks.verify::<RegisteredClaims>(&token("2024-02"), &validation()).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // Unknown `kid`s within `min_refresh_interval` of a reload are rejected without fetching.
+        for _ in 0..3 {
+            let err = jwks.verify::<RegisteredClaims>(&token("bogus"), &validation()).unwrap_err();
+            assert_eq!(err, ValidationError::UnknownKeyId("bogus".to_string()));
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // Keys dropped upstream disappear after the next scheduled reload.
+        *upstream.lock().unwrap() = Ok(oct_set(&["2024-02"]));
+        clock.advance(600);
+        let err = jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap_err();
+        assert_eq!(err, ValidationError::UnknownKeyId("2024-01".to_string()));
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn expires_stale_keys_when_reloading_fails() {
+        let upstream = Arc::new(Mutex::new(Ok(oct_set(&["2024-01"]))));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let clock = Arc::new(ManualClock::default());
+        let jwks = cache(&upstream, &fetches, &clock);
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+
+        *upstream.lock().unwrap() = Err("connection refused".to_string());
+        clock.advance(30 * 60);
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        clock.advance(30 * 60);
+        let err = jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap_err();
+        assert_eq!(err, ValidationError::KeySetUnavailable("connection refused".to_string()));
+
+        *upstream.lock().unwrap() = Ok(oct_set(&["2024-01"]));
+        jwks.refresh().unwrap();
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+    }
+
+    #[test]
+    fn serves_cached_keys_while_reloading() {
+        let (started, reload_started) = mpsc::channel();
+        let (release, released) = mpsc::channel::<()>();
+        let released = Mutex::new(released);
+        let fetches = AtomicUsize::new(0);
+        let clock = Arc::new(ManualClock::default());
+        let jwks = Arc::new(
+            CachedJwks::from_fetch(move || {
+                // every reload after the first blocks until the test releases it.
+                if fetches.fetch_add(1, Ordering::SeqCst) > 0 {
+                    started.send(()).unwrap();
+                    released.lock().unwrap().recv().unwrap();
+                }
+                Ok(oct_set(&["2024-01"]))
+            })
+            .with_refresh_interval(Duration::minutes(10))
+            .with_clock(clock.clone()),
+        );
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+
+        clock.advance(600);
+        let reloading = thread::spawn({
+            let jwks = jwks.clone();
+            move || jwks.keys().map(|_| ())
+        });
+        reload_started.recv().unwrap();
+        jwks.verify::<RegisteredClaims>(&token("2024-01"), &validation()).unwrap();
+        release.send(()).unwrap();
+        reloading.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn refreshes_in_the_background() {
+        let (fetched, fetches) = mpsc::channel();
+        let jwks = Arc::new(
+            CachedJwks::from_fetch(move || {
+                let _ = fetched.send(());
+                Ok(oct_set(&["2024-01"]))
+            })
+            .with_refresh_interval(Duration::milliseconds(10))
+            .with_min_refresh_interval(Duration::milliseconds(10)),
+        );
+        let refresher = jwks.spawn_refresh();
+        for _ in 0..2 {
+            fetches.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+        }
+
+        drop(jwks);
+        refresher.join().unwrap();
+    }
+
+    #[test]
+    fn waits_between_background_refreshes() {
+        let (fetched, fetches) = mpsc::channel();
+        let jwks = Arc::new(
+            CachedJwks::from_fetch(move || {
+                let _ = fetched.send(());
+                Ok(oct_set(&["2024-01"]))
+            })
+            .with_refresh_interval(Duration::zero())
+            .with_min_refresh_interval(Duration::seconds(-1)),
+        );
+        jwks.spawn_refresh();
+        fetches.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+        // the thread sleeps a second rather than reloading in a loop.
+        assert!(fetches.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn loads_from_path() {
+        let path = std::env::temp_dir().join(format!("jwt-claims-jwks-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&oct_set(&["file"])).unwrap()).unwrap();
+        let jwks = CachedJwks::from_path(&path);
+        let verified = jwks.verify::<RegisteredClaims>(&token("file"), &validation());
+        std::fs::remove_file(&path).unwrap();
+        assert!(verified.is_ok());
+
+        let err = CachedJwks::from_path(&path).keys().unwrap_err();
+        assert_eq!(err.reason(), "key_set_unavailable");
+    }
+}
@@ -1,20 +1,141 @@
 //! Structured version of the JWT Claims Set, as referenced at https://datatracker.ietf.org/doc/html/rfc7519#section-4.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, TimestampSeconds};
 use subtle::ConstantTimeEq;
 use thiserror::Error;
 
+pub use crate::{
+    audience::Audience,
+    claims::Claims,
+    replay::{MemoryReplayStore, ReplayStore},
+    revocation::{FileRevocationList, MemoryRevocationList, RevocationList, RevocationSnapshot},
+    time::{Clock, FixedClock, Leeway, OffsetClock, SystemClock},
+    validation::{Claim, Validation},
+};
+
+mod audience;
+mod claims;
+#[cfg(any(feature = "salvo", feature = "axum", feature = "actix", feature = "tonic", feature = "tower"))]
+pub mod integrations;
+#[cfg(feature = "jwe")]
+pub mod jwe;
+pub mod jws;
+mod replay;
+mod revocation;
+mod time;
+mod validation;
+
 // Define specific JWT validation errors as an enum
-#[derive(Error, Debug)]
+//
+// Each variant carries the offending claim value and, for the time-based checks, how far off it was.
+// `Multiple` is only produced when a `Validation` is configured to collect every failure.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum ValidationError {
-    #[error("token is expired")]
-    TokenExpired,
-    #[error("token used before issued")]
-    TokenUsedBeforeIssued,
-    #[error("token is not valid yet")]
-    TokenNotValidYet,
+    #[error("token is expired by {}s", .expired_by.num_seconds())]
+    TokenExpired { expires_at: DateTime<Utc>, expired_by: Duration },
+    #[error("token used before issued, issued in {}s", .issued_in.num_seconds())]
+    TokenUsedBeforeIssued { issued_at: DateTime<Utc>, issued_in: Duration },
+    #[error("token is not valid yet, valid in {}s", .valid_in.num_seconds())]
+    TokenNotValidYet { not_before: DateTime<Utc>, valid_in: Duration },
+    #[error("token is too old, issued {}s ago", .age.num_seconds())]
+    TokenTooOld { issued_at: DateTime<Utc>, age: Duration },
+    #[error("token has invalid audience `{}`", .0.as_slice().join(", "))]
+    InvalidAudience(Audience),
+    #[error("token has invalid issuer `{0}`")]
+    InvalidIssuer(String),
+    #[error("token has invalid subject `{0}`")]
+    InvalidSubject(String),
+    #[error("token has invalid id `{0}`")]
+    InvalidId(String),
+    #[error("token `{0}` has already been used")]
+    TokenReplayed(String),
+    #[error("token is revoked by its `{0}` claim")]
+    TokenRevoked(Claim),
+    #[error("token is missing required claim `{0}`")]
+    MissingRequiredClaim(Claim),
+    #[error("token is malformed: {0}")]
+    MalformedToken(String),
+    #[error("token signature is invalid")]
+    InvalidSignature,
+    #[error("key is invalid: {0}")]
+    InvalidKey(String),
+    #[error("key cannot be used with algorithm {0}")]
+    IncompatibleKey(jws::Algorithm),
+    #[error("token is unsecured, `alg` is `none`")]
+    UnsecuredToken,
+    #[error("token algorithm {0} is not allowed for this key")]
+    AlgorithmNotAllowed(jws::Algorithm),
+    #[error("token has unsupported critical header `{0}`")]
+    UnsupportedCriticalHeader(String),
+    #[error("token has invalid type `{0}`")]
+    InvalidTokenType(String),
+    #[error("request carries no token")]
+    MissingToken,
+    #[error("token does not grant `{0}`")]
+    InsufficientScope(String),
+    #[error("token does not grant the role `{0}`")]
+    MissingRole(String),
+    #[error("token could not be decrypted")]
+    DecryptionFailed,
+    #[error("token encryption algorithm {0} is not allowed for this key")]
+    EncryptionNotAllowed(String),
+    #[error("token is nested more than {0} levels deep")]
+    NestingTooDeep(usize),
+    #[error("token has unknown key id `{0}`")]
+    UnknownKeyId(String),
+    #[error("token has no key id to select a key with")]
+    MissingKeyId,
+    #[error("key set is unavailable: {0}")]
+    KeySetUnavailable(String),
+    #[error("token is invalid: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Multiple(Vec<ValidationError>),
+}
+
+impl ValidationError {
+    // Returns a stable, machine-readable name for the failure, suitable as a metrics label.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ValidationError::TokenExpired { .. } => "token_expired",
+            ValidationError::TokenUsedBeforeIssued { .. } => "token_used_before_issued",
+            ValidationError::TokenNotValidYet { .. } => "token_not_valid_yet",
+            ValidationError::TokenTooOld { .. } => "token_too_old",
+            ValidationError::InvalidAudience(_) => "invalid_audience",
+            ValidationError::InvalidIssuer(_) => "invalid_issuer",
+            ValidationError::InvalidSubject(_) => "invalid_subject",
+            ValidationError::InvalidId(_) => "invalid_id",
+            ValidationError::TokenReplayed(_) => "token_replayed",
+            ValidationError::TokenRevoked(_) => "token_revoked",
+            ValidationError::MissingRequiredClaim(_) => "missing_required_claim",
+            ValidationError::MalformedToken(_) => "malformed_token",
+            ValidationError::InvalidSignature => "invalid_signature",
+            ValidationError::InvalidKey(_) => "invalid_key",
+            ValidationError::IncompatibleKey(_) => "incompatible_key",
+            ValidationError::UnsecuredToken => "unsecured_token",
+            ValidationError::AlgorithmNotAllowed(_) => "algorithm_not_allowed",
+            ValidationError::UnsupportedCriticalHeader(_) => "unsupported_critical_header",
+            ValidationError::InvalidTokenType(_) => "invalid_token_type",
+            ValidationError::MissingToken => "missing_token",
+            ValidationError::InsufficientScope(_) => "insufficient_scope",
+            ValidationError::MissingRole(_) => "missing_role",
+            ValidationError::DecryptionFailed => "decryption_failed",
+            ValidationError::EncryptionNotAllowed(_) => "encryption_not_allowed",
+            ValidationError::NestingTooDeep(_) => "nesting_too_deep",
+            ValidationError::UnknownKeyId(_) => "unknown_key_id",
+            ValidationError::MissingKeyId => "missing_key_id",
+            ValidationError::KeySetUnavailable(_) => "key_set_unavailable",
+            ValidationError::Multiple(_) => "multiple",
+        }
+    }
+
+    // Returns the individual failures: the collected ones for `Multiple`, otherwise just `self`.
+    pub fn errors(&self) -> &[ValidationError] {
+        match self {
+            ValidationError::Multiple(errors) => errors,
+            _ => std::slice::from_ref(self),
+        }
+    }
 }
 
 // RegisteredClaims are a structured version of the JWT Claims Set,
@@ -28,7 +149,7 @@ pub enum ValidationError {
 // See examples for how to use this with your own claim types.
 #[serde_as]
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
 #[serde(default)]
 pub struct RegisteredClaims {
     // the `iss` (Issuer) claim. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.1
@@ -40,8 +161,8 @@ pub struct RegisteredClaims {
     pub subject: String,
 
     // the `aud` (Audience) claim. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.3
-    #[serde(rename = "aud", skip_serializing_if = "Vec::is_empty")]
-    pub audience: Vec<String>,
+    #[serde(rename = "aud", skip_serializing_if = "Audience::is_empty")]
+    pub audience: Audience,
 
     // the `exp` (Expiration Time) claim. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.4
     #[serde(rename = "exp", skip_serializing_if = "Option::is_none")]
@@ -49,12 +170,12 @@ pub struct RegisteredClaims {
     pub expires_at: Option<DateTime<Utc>>,
 
     // the `nbf` (Not Before) claim. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.5
-    #[serde(rename = "exp", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "nbf", skip_serializing_if = "Option::is_none")]
     #[serde_as(as = "Option<TimestampSeconds<i64>>")]
     pub not_before: Option<DateTime<Utc>>,
 
     // the `iat` (Issued At) claim. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.6
-    #[serde(rename = "exp", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "iat", skip_serializing_if = "Option::is_none")]
     #[serde_as(as = "Option<TimestampSeconds<i64>>")]
     pub issued_at: Option<DateTime<Utc>>,
 
@@ -63,19 +184,58 @@ pub struct RegisteredClaims {
     pub id: String,
 }
 
+impl AsRef<RegisteredClaims> for RegisteredClaims {
+    fn as_ref(&self) -> &RegisteredClaims {
+        self
+    }
+}
+
 impl RegisteredClaims {
     pub fn valid(&self) -> Result<(), ValidationError> {
-        let now = Utc::now();
-        if !self.verify_expires_at(now, false) {
-            return Err(ValidationError::TokenExpired);
+        self.valid_with_leeway(&Leeway::default())
+    }
+
+    // Like `valid`, but tolerates the given clock skew on the `exp`, `iat` and `nbf` claims.
+    pub fn valid_with_leeway(&self, leeway: &Leeway) -> Result<(), ValidationError> {
+        self.valid_at(&SystemClock, leeway)
+    }
+
+    // Like `valid_with_leeway`, but reads the current time from `clock`.
+    pub fn valid_at(&self, clock: &dyn Clock, leeway: &Leeway) -> Result<(), ValidationError> {
+        match self.time_errors(clock.now(), leeway).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
         }
-        if !self.verify_issued_at(now, false) {
-            return Err(ValidationError::TokenUsedBeforeIssued);
+    }
+
+    // Checks `exp`, `iat` and `nbf` against `now`, returning every failure in that order.
+    pub(crate) fn time_errors(&self, now: DateTime<Utc>, leeway: &Leeway) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if !self.verify_expires_at_with_leeway(now, false, leeway.expires_at) {
+            if let Some(expires_at) = self.expires_at {
+                errors.push(ValidationError::TokenExpired {
+                    expires_at,
+                    expired_by: now - expires_at,
+                });
+            }
+        }
+        if !self.verify_issued_at_with_leeway(now, false, leeway.not_before) {
+            if let Some(issued_at) = self.issued_at {
+                errors.push(ValidationError::TokenUsedBeforeIssued {
+                    issued_at,
+                    issued_in: issued_at - now,
+                });
+            }
         }
-        if !self.verify_not_before(now, false) {
-            return Err(ValidationError::TokenNotValidYet);
+        if !self.verify_not_before_with_leeway(now, false, leeway.not_before) {
+            if let Some(not_before) = self.not_before {
+                errors.push(ValidationError::TokenNotValidYet {
+                    not_before,
+                    valid_in: not_before - now,
+                });
+            }
         }
-        Ok(())
+        errors
     }
 
     pub fn verify_audience(&self, cmp: &str, required: bool) -> bool {
@@ -85,7 +245,7 @@ impl RegisteredClaims {
 
         let mut result = false;
         let mut string_claims = String::new();
-        for a in self.audience.iter() {
+        for a in &self.audience {
             if a.as_bytes().ct_eq(cmp.as_bytes()).unwrap_u8() == 1 {
                 result = true;
             }
@@ -99,33 +259,46 @@ impl RegisteredClaims {
     }
 
     pub fn verify_expires_at(&self, cmp: DateTime<Utc>, required: bool) -> bool {
+        self.verify_expires_at_with_leeway(cmp, required, Duration::zero())
+    }
+
+    pub fn verify_expires_at_with_leeway(&self, cmp: DateTime<Utc>, required: bool, leeway: Duration) -> bool {
         if let Some(ref exp) = self.expires_at {
             if exp.timestamp() == 0 {
                 return !required;
             }
-            cmp < *exp
+            // a deadline past the end of time is never reached.
+            exp.checked_add_signed(leeway).map_or(leeway > Duration::zero(), |exp| cmp < exp)
         } else {
             !required
         }
     }
 
     pub fn verify_issued_at(&self, cmp: DateTime<Utc>, required: bool) -> bool {
+        self.verify_issued_at_with_leeway(cmp, required, Duration::zero())
+    }
+
+    pub fn verify_issued_at_with_leeway(&self, cmp: DateTime<Utc>, required: bool, leeway: Duration) -> bool {
         if let Some(ref iat) = self.issued_at {
             if iat.timestamp() == 0 {
                 return !required;
             }
-            cmp >= *iat
+            cmp.checked_add_signed(leeway).map_or(leeway > Duration::zero(), |cmp| cmp >= *iat)
         } else {
             !required
         }
     }
 
     pub fn verify_not_before(&self, cmp: DateTime<Utc>, required: bool) -> bool {
+        self.verify_not_before_with_leeway(cmp, required, Duration::zero())
+    }
+
+    pub fn verify_not_before_with_leeway(&self, cmp: DateTime<Utc>, required: bool, leeway: Duration) -> bool {
         if let Some(ref nbf) = self.not_before {
             if nbf.timestamp() == 0 {
                 return !required;
             }
-            cmp >= *nbf
+            cmp.checked_add_signed(leeway).map_or(leeway > Duration::zero(), |cmp| cmp >= *nbf)
         } else {
             !required
         }
@@ -137,6 +310,20 @@ impl RegisteredClaims {
         }
         self.issuer.as_bytes().ct_eq(cmp.as_bytes()).unwrap_u8() == 1
     }
+
+    pub fn verify_subject(&self, cmp: &str, required: bool) -> bool {
+        if self.subject.is_empty() {
+            return !required;
+        }
+        self.subject.as_bytes().ct_eq(cmp.as_bytes()).unwrap_u8() == 1
+    }
+
+    pub fn verify_id(&self, cmp: &str, required: bool) -> bool {
+        if self.id.is_empty() {
+            return !required;
+        }
+        self.id.as_bytes().ct_eq(cmp.as_bytes()).unwrap_u8() == 1
+    }
 }
 
 #[cfg(test)]
@@ -150,27 +337,279 @@ mod tests {
         let claims = RegisteredClaims {
             issuer: "issuer".to_string(),
             subject: "subject".to_string(),
-            audience: vec!["aud1".to_string(), "aud2".to_string()],
+            audience: vec!["aud1".to_string(), "aud2".to_string()].into(),
             expires_at: Some(Utc.with_ymd_and_hms(2023, 10, 1, 0, 0, 0).unwrap()),
             not_before: Some(Utc.with_ymd_and_hms(2021, 10, 1, 0, 0, 0).unwrap()),
             issued_at: Some(Utc.with_ymd_and_hms(2021, 10, 1, 0, 0, 0).unwrap()),
             id: "jti".to_string(),
         };
 
-        match claims.valid() {
-            Ok(_) => println!("Claims are valid."),
-            Err(e) => println!("Claims are invalid: {}", e),
-        }
+        let leeway = Leeway::default();
+        assert!(claims
+            .valid_at(&FixedClock(Utc.with_ymd_and_hms(2022, 10, 1, 0, 0, 0).unwrap()), &leeway)
+            .is_ok());
+        assert_eq!(
+            claims.valid_at(&FixedClock(Utc.with_ymd_and_hms(2023, 10, 1, 0, 0, 0).unwrap()), &leeway),
+            Err(ValidationError::TokenExpired {
+                expires_at: Utc.with_ymd_and_hms(2023, 10, 1, 0, 0, 0).unwrap(),
+                expired_by: Duration::zero(),
+            })
+        );
+        assert_eq!(
+            claims.valid_at(&FixedClock(Utc.with_ymd_and_hms(2021, 9, 30, 0, 0, 0).unwrap()), &leeway),
+            Err(ValidationError::TokenUsedBeforeIssued {
+                issued_at: Utc.with_ymd_and_hms(2021, 10, 1, 0, 0, 0).unwrap(),
+                issued_in: Duration::days(1),
+            })
+        );
 
-        if claims.verify_audience("aud1", true) {
-            println!("Audience is valid.");
-        } else {
-            println!("Audience is invalid.");
-        }
+        assert!(claims.verify_audience("aud1", true));
+        assert!(!claims.verify_audience("aud3", true));
 
         assert_eq!(
-            r##"{"iss":"issuer","sub":"subject","aud":["aud1","aud2"],"exp":1696118400,"exp":1633046400,"exp":1633046400,"jti":"jti"}"##,
+            r##"{"iss":"issuer","sub":"subject","aud":["aud1","aud2"],"exp":1696118400,"nbf":1633046400,"iat":1633046400,"jti":"jti"}"##,
             serde_json::to_string(&claims).unwrap()
         )
     }
+
+    fn ts(secs: i64) -> Option<DateTime<Utc>> {
+        Some(Utc.timestamp_opt(secs, 0).unwrap())
+    }
+
+    // Parses `input`, checks it against `expected`, then serializes and parses it again to make sure nothing is lost on the way back.
+    fn round_trip(input: &str, expected: &RegisteredClaims) {
+        let claims: RegisteredClaims = serde_json::from_str(input).unwrap();
+        assert_claims_eq(&claims, expected);
+        let encoded = serde_json::to_string(&claims).unwrap();
+        let decoded: RegisteredClaims = serde_json::from_str(&encoded).unwrap();
+        assert_claims_eq(&decoded, expected);
+    }
+
+    fn assert_claims_eq(a: &RegisteredClaims, b: &RegisteredClaims) {
+        assert_eq!(a.issuer, b.issuer);
+        assert_eq!(a.subject, b.subject);
+        assert_eq!(a.audience, b.audience);
+        assert_eq!(a.expires_at, b.expires_at);
+        assert_eq!(a.not_before, b.not_before);
+        assert_eq!(a.issued_at, b.issued_at);
+        assert_eq!(a.id, b.id);
+    }
+
+    #[test]
+    fn serializes_registered_claim_names() {
+        let claims = RegisteredClaims {
+            expires_at: ts(1300819380),
+            not_before: ts(1300819000),
+            issued_at: ts(1300818000),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&claims).unwrap();
+        assert_eq!(value["exp"], 1300819380);
+        assert_eq!(value["nbf"], 1300819000);
+        assert_eq!(value["iat"], 1300818000);
+        assert_eq!(value.as_object().unwrap().len(), 3);
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc7519#section-3.1
+    #[test]
+    fn conformance_rfc7519_example() {
+        round_trip(
+            "{\"iss\":\"joe\",\r\n \"exp\":1300819380,\r\n \"http://example.com/is_root\":true}",
+            &RegisteredClaims {
+                issuer: "joe".to_string(),
+                expires_at: ts(1300819380),
+                ..Default::default()
+            },
+        );
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc7519#section-6.1
+    #[test]
+    fn conformance_rfc7519_unsecured_example() {
+        round_trip(
+            r#"{"iss":"joe","exp":1300819380,"http://example.com/is_root":true}"#,
+            &RegisteredClaims {
+                issuer: "joe".to_string(),
+                expires_at: ts(1300819380),
+                ..Default::default()
+            },
+        );
+    }
+
+    // The default token shown on jwt.io, as issued by most libraries' quick-start examples.
+    #[test]
+    fn conformance_jwt_io_example() {
+        round_trip(
+            r#"{"sub":"1234567890","name":"John Doe","iat":1516239022}"#,
+            &RegisteredClaims {
+                subject: "1234567890".to_string(),
+                issued_at: ts(1516239022),
+                ..Default::default()
+            },
+        );
+    }
+
+    // golang-jwt/jwt `ExampleNew_hmac`.
+    #[test]
+    fn conformance_golang_jwt_example() {
+        round_trip(
+            r#"{"foo":"bar","nbf":1444478400}"#,
+            &RegisteredClaims {
+                not_before: ts(1444478400),
+                ..Default::default()
+            },
+        );
+    }
+
+    // An Auth0 access token for a custom API.
+    #[test]
+    fn conformance_auth0_access_token() {
+        round_trip(
+            r#"{"iss":"https://example.auth0.com/","sub":"auth0|5f7c8ec7c33c6c004bbafe82","aud":["https://api.example.com","https://example.auth0.com/userinfo"],"iat":1700000000,"exp":1700086400,"azp":"Wf1ox8ZgCZTnE6Fv2Cb5vyk5jZaGkrFC","scope":"openid profile"}"#,
+            &RegisteredClaims {
+                issuer: "https://example.auth0.com/".to_string(),
+                subject: "auth0|5f7c8ec7c33c6c004bbafe82".to_string(),
+                audience: vec!["https://api.example.com".to_string(), "https://example.auth0.com/userinfo".to_string()].into(),
+                expires_at: ts(1700086400),
+                issued_at: ts(1700000000),
+                ..Default::default()
+            },
+        );
+    }
+
+    // A Keycloak access token, which carries every registered claim.
+    #[test]
+    fn conformance_keycloak_access_token() {
+        round_trip(
+            r#"{"exp":1700000300,"iat":1700000000,"nbf":0,"jti":"6f2c6c1e-8a7b-4c57-9a0e-1d5c1f0b2a3d","iss":"http://localhost:8080/realms/master","aud":["account"],"sub":"f1b0a5a4-3c2e-4b8f-9d1e-7a6c5b4d3e2f","typ":"Bearer","azp":"admin-cli"}"#,
+            &RegisteredClaims {
+                issuer: "http://localhost:8080/realms/master".to_string(),
+                subject: "f1b0a5a4-3c2e-4b8f-9d1e-7a6c5b4d3e2f".to_string(),
+                audience: vec!["account".to_string()].into(),
+                expires_at: ts(1700000300),
+                not_before: ts(0),
+                issued_at: ts(1700000000),
+                id: "6f2c6c1e-8a7b-4c57-9a0e-1d5c1f0b2a3d".to_string(),
+            },
+        );
+    }
+
+    // The claims produced by the `jsonwebtoken` crate's README example.
+    #[test]
+    fn conformance_jsonwebtoken_example() {
+        round_trip(
+            r#"{"aud":["me"],"sub":"b@b.com","company":"ACME","exp":10000000000}"#,
+            &RegisteredClaims {
+                subject: "b@b.com".to_string(),
+                audience: vec!["me".to_string()].into(),
+                expires_at: ts(10000000000),
+                ..Default::default()
+            },
+        );
+    }
+
+    // An Azure AD v2.0 access token, which uses the single string form of `aud`.
+    #[test]
+    fn conformance_azure_ad_access_token() {
+        round_trip(
+            r#"{"aud":"6e74172b-be56-4843-9ff4-e66a39bb12e3","iss":"https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/v2.0","iat":1537231048,"nbf":1537231048,"exp":1537234948,"sub":"AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ","ver":"2.0"}"#,
+            &RegisteredClaims {
+                issuer: "https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/v2.0".to_string(),
+                subject: "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ".to_string(),
+                audience: "6e74172b-be56-4843-9ff4-e66a39bb12e3".into(),
+                expires_at: ts(1537234948),
+                not_before: ts(1537231048),
+                issued_at: ts(1537231048),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn verify_audience_accepts_both_forms() {
+        let mut claims: RegisteredClaims = serde_json::from_str(r#"{"aud":"api"}"#).unwrap();
+        assert!(claims.verify_audience("api", true));
+        assert!(!claims.verify_audience("web", true));
+        assert_eq!(serde_json::to_string(&claims).unwrap(), r#"{"aud":"api"}"#);
+
+        claims.audience = vec!["web", "api"].into();
+        assert!(claims.verify_audience("api", true));
+        assert_eq!(serde_json::to_string(&claims).unwrap(), r#"{"aud":["web","api"]}"#);
+
+        claims.audience = Audience::default();
+        assert!(claims.verify_audience("api", false));
+        assert!(!claims.verify_audience("api", true));
+    }
+
+    #[test]
+    fn verify_time_claims_with_leeway() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let claims = RegisteredClaims {
+            expires_at: Some(at),
+            not_before: Some(at),
+            issued_at: Some(at),
+            ..Default::default()
+        };
+        let two = Duration::seconds(2);
+
+        let late = at + Duration::seconds(1);
+        assert!(!claims.verify_expires_at(late, true));
+        assert!(claims.verify_expires_at_with_leeway(late, true, two));
+        assert!(!claims.verify_expires_at_with_leeway(late + two, true, two));
+
+        let early = at - Duration::seconds(1);
+        assert!(!claims.verify_issued_at(early, true));
+        assert!(claims.verify_issued_at_with_leeway(early, true, two));
+        assert!(!claims.verify_not_before(early, true));
+        assert!(claims.verify_not_before_with_leeway(early, true, two));
+        assert!(!claims.verify_not_before_with_leeway(early - two, true, two));
+    }
+
+    #[test]
+    fn leeway_does_not_overflow_at_the_end_of_time() {
+        let end = DateTime::<Utc>::MAX_UTC.timestamp();
+        let claims: RegisteredClaims = serde_json::from_value(serde_json::json!({ "exp": end, "nbf": end, "iat": 1704067200 })).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        assert!(claims.verify_expires_at_with_leeway(now, true, Duration::seconds(60)));
+        assert!(claims.verify_not_before_with_leeway(now, true, Duration::MAX));
+        assert!(claims.verify_issued_at_with_leeway(now, true, Duration::MAX));
+        assert!(claims.valid_at(&FixedClock(now), &Leeway::seconds(60)).is_err());
+        assert!(Validation::new()
+            .with_leeway(Leeway::new(Duration::MAX))
+            .with_max_age(Duration::MAX)
+            .validate_at(&claims, &FixedClock(now))
+            .is_ok());
+    }
+
+    #[test]
+    fn valid_with_leeway() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = FixedClock(now);
+        let claims = RegisteredClaims {
+            expires_at: Some(now - Duration::seconds(5)),
+            not_before: Some(now + Duration::seconds(5)),
+            ..Default::default()
+        };
+        let err = claims.valid_at(&clock, &Leeway::default()).unwrap_err();
+        assert_eq!(err.reason(), "token_expired");
+        assert_eq!(err.to_string(), "token is expired by 5s");
+        assert!(matches!(
+            claims.valid_at(&clock, &Leeway::default().with_expires_at(Duration::seconds(60))),
+            Err(ValidationError::TokenNotValidYet { .. })
+        ));
+        assert!(claims.valid_at(&clock, &Leeway::seconds(60)).is_ok());
+    }
+
+    #[test]
+    fn valid_at_offset_clock() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let claims = RegisteredClaims {
+            expires_at: Some(now),
+            ..Default::default()
+        };
+        let before = OffsetClock::new(FixedClock(now), Duration::seconds(-1));
+        assert!(claims.valid_at(&before, &Leeway::default()).is_ok());
+        let after = OffsetClock::new(FixedClock(now), Duration::seconds(1));
+        assert!(claims.valid_at(&after, &Leeway::default()).is_err());
+    }
 }
@@ -0,0 +1,212 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{Claim, Clock, Leeway, RegisteredClaims, SystemClock, ValidationError};
+
+// ReplayStore remembers the `jti` of the tokens that have been used, so each token is accepted only once, as
+// one-time tokens such as password-reset or invitation links require. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.7
+//
+// `MemoryReplayStore` keeps the identifiers in the process; a store shared between processes, e.g. a cache
+// server, implements `insert` on top of an atomic "set if absent" with an expiry. A `Validation` consults its
+// store once a token has passed every other check, see `Validation::with_replay_store`.
+pub trait ReplayStore: fmt::Debug + Send + Sync {
+    // Records `id` as used until `expires_at`, and reports whether it was new. An identifier whose record expired
+    // at `now` counts as new. Concurrent calls with the same identifier must return true at most once.
+    fn insert(&self, id: &str, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool;
+
+    // Accepts `claims` the first time its `jti` is seen and rejects it with `TokenReplayed` afterwards, until the
+    // token expires. Both `jti` and `exp` are required, since a token without them cannot be told apart or forgotten.
+    //
+    // This consumes the token, so it should be the last step, once the signature and claims have been validated.
+    fn check(&self, claims: &RegisteredClaims, leeway: &Leeway) -> Result<(), ValidationError> {
+        self.check_at(claims, leeway, &SystemClock)
+    }
+
+    // Like `check`, but reads the current time from `clock`.
+    fn check_at(&self, claims: &RegisteredClaims, leeway: &Leeway, clock: &dyn Clock) -> Result<(), ValidationError> {
+        if !Claim::Id.is_present(claims) {
+            return Err(ValidationError::MissingRequiredClaim(Claim::Id));
+        }
+        let expires_at = match claims.expires_at {
+            Some(expires_at) if Claim::ExpiresAt.is_present(claims) => expires_at,
+            _ => return Err(ValidationError::MissingRequiredClaim(Claim::ExpiresAt)),
+        };
+        // the token is accepted until `exp` plus the leeway, so it must be remembered as long, or forever if that
+        // is past the end of time.
+        let expires_at = expires_at.checked_add_signed(leeway.expires_at).unwrap_or(DateTime::<Utc>::MAX_UTC);
+        if self.insert(&claims.id, expires_at, clock.now()) {
+            Ok(())
+        } else {
+            Err(ValidationError::TokenReplayed(claims.id.clone()))
+        }
+    }
+}
+
+impl<S: ReplayStore + ?Sized> ReplayStore for &S {
+    fn insert(&self, id: &str, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        (**self).insert(id, expires_at, now)
+    }
+}
+
+impl<S: ReplayStore + ?Sized> ReplayStore for Box<S> {
+    fn insert(&self, id: &str, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        (**self).insert(id, expires_at, now)
+    }
+}
+
+impl<S: ReplayStore + ?Sized> ReplayStore for Arc<S> {
+    fn insert(&self, id: &str, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        (**self).insert(id, expires_at, now)
+    }
+}
+
+// MemoryReplayStore keeps the used identifiers in memory, each until its token expires. Expired entries are
+// dropped whenever an identifier is inserted, in order of expiry, so an insertion only visits the entries it drops.
+#[derive(Debug, Default)]
+pub struct MemoryReplayStore {
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    ids: HashMap<String, DateTime<Utc>>,
+    expiries: BTreeMap<DateTime<Utc>, Vec<String>>,
+}
+
+impl Entries {
+    // Drops the identifiers whose record expired at `now`.
+    fn purge(&mut self, now: DateTime<Utc>) {
+        while let Some(entry) = self.expiries.first_entry() {
+            if *entry.key() > now {
+                break;
+            }
+            for id in entry.remove() {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+impl MemoryReplayStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the number of identifiers remembered, including those expired since the last insertion.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ReplayStore for MemoryReplayStore {
+    fn insert(&self, id: &str, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.purge(now);
+        if entries.ids.contains_key(id) {
+            return false;
+        }
+        entries.ids.insert(id.to_string(), expires_at);
+        entries.expiries.entry(expires_at).or_default().push(id.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone as _};
+
+    use super::*;
+    use crate::FixedClock;
+
+    fn claims(id: &str) -> RegisteredClaims {
+        RegisteredClaims {
+            id: id.to_string(),
+            expires_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn clock(minute: u32) -> FixedClock {
+        FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 0, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn rejects_reused_ids() {
+        let store = MemoryReplayStore::new();
+        let leeway = Leeway::default();
+        assert_eq!(store.check_at(&claims("reset-1"), &leeway, &clock(0)), Ok(()));
+        assert_eq!(store.check_at(&claims("reset-2"), &leeway, &clock(0)), Ok(()));
+        assert_eq!(
+            store.check_at(&claims("reset-1"), &leeway, &clock(30)),
+            Err(ValidationError::TokenReplayed("reset-1".to_string()))
+        );
+        assert_eq!(store.len(), 2);
+
+        let mut claims = claims("");
+        assert_eq!(
+            store.check_at(&claims, &leeway, &clock(0)),
+            Err(ValidationError::MissingRequiredClaim(Claim::Id))
+        );
+        claims.id = "reset-3".to_string();
+        claims.expires_at = None;
+        assert_eq!(
+            store.check_at(&claims, &leeway, &clock(0)),
+            Err(ValidationError::MissingRequiredClaim(Claim::ExpiresAt))
+        );
+    }
+
+    #[test]
+    fn forgets_ids_once_expired() {
+        let store = MemoryReplayStore::new();
+        let leeway = Leeway::new(Duration::minutes(5));
+        let after_exp = FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 1, 3, 0).unwrap());
+        let after_leeway = FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 1, 5, 0).unwrap());
+
+        assert!(store.check_at(&claims("invite"), &leeway, &clock(0)).is_ok());
+        assert!(store.check_at(&claims("invite"), &leeway, &after_exp).is_err());
+        assert!(store.check_at(&claims("other"), &leeway, &after_leeway).is_ok());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn remembers_ids_forever_past_the_end_of_time() {
+        let store = MemoryReplayStore::new();
+        let mut claims = claims("reset");
+        claims.expires_at = Some(DateTime::<Utc>::MAX_UTC);
+        let leeway = Leeway::new(Duration::minutes(5));
+        assert_eq!(store.check_at(&claims, &leeway, &clock(0)), Ok(()));
+        let far_future = FixedClock(Utc.with_ymd_and_hms(9999, 1, 1, 0, 0, 0).unwrap());
+        assert!(store.check_at(&claims, &leeway, &far_future).is_err());
+    }
+
+    #[test]
+    fn validation_consumes_valid_tokens_only() {
+        let store = Arc::new(MemoryReplayStore::new());
+        let validation = crate::Validation::new().with_issuer("joe").with_replay_store(store.clone());
+
+        let mut claims = claims("invite");
+        let err = validation.validate_at(&claims, &clock(0)).unwrap_err();
+        assert_eq!(err, ValidationError::MissingRequiredClaim(Claim::Issuer));
+        assert!(store.is_empty());
+
+        claims.issuer = "joe".to_string();
+        assert_eq!(validation.validate_at(&claims, &clock(0)), Ok(()));
+        assert_eq!(
+            validation.clone().validate_at(&claims, &clock(1)),
+            Err(ValidationError::TokenReplayed("invite".to_string()))
+        );
+
+        claims.id = String::new();
+        let err = validation.with_collect_all(true).validate_at(&claims, &clock(0)).unwrap_err();
+        assert_eq!(err, ValidationError::MissingRequiredClaim(Claim::Id));
+    }
+}
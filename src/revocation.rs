@@ -0,0 +1,447 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, TimestampSeconds};
+
+use crate::{Claim, RegisteredClaims, ValidationError};
+
+// RevocationList tells which tokens have been revoked before they expire: single tokens by their `jti`, every
+// token of a subject issued before a cutoff, e.g. after a password change, or every token of an issuer.
+//
+// `MemoryRevocationList` keeps the revocations in the process and `FileRevocationList` persists them as a
+// `RevocationSnapshot`; other backends, e.g. a database, implement the three lookups. A `Validation` rejects the
+// tokens its list revokes, see `Validation::with_revocation_list`.
+pub trait RevocationList: fmt::Debug + Send + Sync {
+    // Reports whether the token with the `jti` `id` has been revoked.
+    fn is_id_revoked(&self, id: &str) -> bool;
+
+    // Returns the time before which the tokens of `subject` were revoked, if they were.
+    fn subject_revoked_before(&self, subject: &str) -> Option<DateTime<Utc>>;
+
+    // Reports whether every token of `issuer` has been revoked.
+    fn is_issuer_revoked(&self, issuer: &str) -> bool;
+
+    // Rejects `claims` with `TokenRevoked`, naming the claim it was revoked by, if the token has been revoked.
+    //
+    // A token is revoked by its subject if its `iat` claim is before the subject's cutoff. A token without `iat`
+    // cannot be placed before or after the cutoff, so it is revoked as well.
+    fn check(&self, claims: &RegisteredClaims) -> Result<(), ValidationError> {
+        if !claims.id.is_empty() && self.is_id_revoked(&claims.id) {
+            return Err(ValidationError::TokenRevoked(Claim::Id));
+        }
+        if !claims.subject.is_empty() {
+            if let Some(cutoff) = self.subject_revoked_before(&claims.subject) {
+                let issued_at = claims.issued_at.filter(|_| Claim::IssuedAt.is_present(claims));
+                if issued_at.is_none_or(|issued_at| issued_at < cutoff) {
+                    return Err(ValidationError::TokenRevoked(Claim::Subject));
+                }
+            }
+        }
+        if !claims.issuer.is_empty() && self.is_issuer_revoked(&claims.issuer) {
+            return Err(ValidationError::TokenRevoked(Claim::Issuer));
+        }
+        Ok(())
+    }
+}
+
+impl<L: RevocationList + ?Sized> RevocationList for &L {
+    fn is_id_revoked(&self, id: &str) -> bool {
+        (**self).is_id_revoked(id)
+    }
+
+    fn subject_revoked_before(&self, subject: &str) -> Option<DateTime<Utc>> {
+        (**self).subject_revoked_before(subject)
+    }
+
+    fn is_issuer_revoked(&self, issuer: &str) -> bool {
+        (**self).is_issuer_revoked(issuer)
+    }
+}
+
+impl<L: RevocationList + ?Sized> RevocationList for Box<L> {
+    fn is_id_revoked(&self, id: &str) -> bool {
+        (**self).is_id_revoked(id)
+    }
+
+    fn subject_revoked_before(&self, subject: &str) -> Option<DateTime<Utc>> {
+        (**self).subject_revoked_before(subject)
+    }
+
+    fn is_issuer_revoked(&self, issuer: &str) -> bool {
+        (**self).is_issuer_revoked(issuer)
+    }
+}
+
+impl<L: RevocationList + ?Sized> RevocationList for Arc<L> {
+    fn is_id_revoked(&self, id: &str) -> bool {
+        (**self).is_id_revoked(id)
+    }
+
+    fn subject_revoked_before(&self, subject: &str) -> Option<DateTime<Utc>> {
+        (**self).subject_revoked_before(subject)
+    }
+
+    fn is_issuer_revoked(&self, issuer: &str) -> bool {
+        (**self).is_issuer_revoked(issuer)
+    }
+}
+
+// RevocationSnapshot is the serialized state of a revocation list, stored as JSON by `FileRevocationList`:
+//
+// {"ids":["4f1g23a12aa"],"subjects":{"alice":1704067200},"issuers":["https://old.example.com"]}
+//
+// where each subject maps to the NumericDate before which its tokens are revoked.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct RevocationSnapshot {
+    pub ids: BTreeSet<String>,
+    #[serde_as(as = "BTreeMap<_, TimestampSeconds<i64>>")]
+    pub subjects: BTreeMap<String, DateTime<Utc>>,
+    pub issuers: BTreeSet<String>,
+}
+
+impl RevocationSnapshot {
+    // Merges a subject cutoff, keeping the latest one.
+    fn revoke_subject(&mut self, subject: &str, before: DateTime<Utc>) {
+        let cutoff = self.subjects.entry(subject.to_string()).or_insert(before);
+        *cutoff = (*cutoff).max(before);
+    }
+
+    // Adds the revocations of `other`.
+    fn merge(&mut self, other: RevocationSnapshot) {
+        self.ids.extend(other.ids);
+        for (subject, before) in other.subjects {
+            self.revoke_subject(&subject, before);
+        }
+        self.issuers.extend(other.issuers);
+    }
+}
+
+// MemoryRevocationList keeps revocations in memory. It can be shared, e.g. in an `Arc`, and revoked from
+// while tokens are checked against it.
+#[derive(Debug, Default)]
+pub struct MemoryRevocationList {
+    snapshot: RwLock<RevocationSnapshot>,
+}
+
+impl MemoryRevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_snapshot(snapshot: RevocationSnapshot) -> Self {
+        MemoryRevocationList {
+            snapshot: RwLock::new(snapshot),
+        }
+    }
+
+    // Returns a copy of the current revocations.
+    pub fn snapshot(&self) -> RevocationSnapshot {
+        self.read().clone()
+    }
+
+    // Replaces every revocation with those of `snapshot`.
+    pub fn replace(&self, snapshot: RevocationSnapshot) {
+        *self.write() = snapshot;
+    }
+
+    // Revokes the token whose `jti` is `id`.
+    pub fn revoke_id(&self, id: impl Into<String>) {
+        self.write().ids.insert(id.into());
+    }
+
+    // Revokes the tokens of `subject` issued before `before`. An earlier cutoff than the current one has no effect.
+    pub fn revoke_subject(&self, subject: &str, before: DateTime<Utc>) {
+        self.write().revoke_subject(subject, before);
+    }
+
+    // Revokes every token of `issuer`.
+    pub fn revoke_issuer(&self, issuer: impl Into<String>) {
+        self.write().issuers.insert(issuer.into());
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, RevocationSnapshot> {
+        self.snapshot.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, RevocationSnapshot> {
+        self.snapshot.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl RevocationList for MemoryRevocationList {
+    fn is_id_revoked(&self, id: &str) -> bool {
+        self.read().ids.contains(id)
+    }
+
+    fn subject_revoked_before(&self, subject: &str) -> Option<DateTime<Utc>> {
+        self.read().subjects.get(subject).copied()
+    }
+
+    fn is_issuer_revoked(&self, issuer: &str) -> bool {
+        self.read().issuers.contains(issuer)
+    }
+}
+
+// FileRevocationList is a `MemoryRevocationList` persisted as a `RevocationSnapshot` in a JSON file.
+//
+// Revoking takes effect in memory first, then adds the revocation to the snapshot stored in the file, so
+// revocations written by other processes are kept, and picked up along the way. Processes that only check tokens
+// call `reload` to pick up revocations written by others.
+//
+// Writers hold an exclusive lock on `<path>.lock` while they update the file. Each writes the new snapshot to a
+// temporary file of its own, syncs it to disk and renames it over the list, so on filesystems where renaming is
+// atomic a reader sees either the previous or the new snapshot, even if the writer crashes.
+#[derive(Debug)]
+pub struct FileRevocationList {
+    path: PathBuf,
+    list: MemoryRevocationList,
+    // serializes the writers of this process, which the lock file does not on every platform.
+    saving: Mutex<()>,
+}
+
+impl FileRevocationList {
+    // Loads the revocations stored at `path`. A missing file is an empty list, created on the first revocation.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let list = MemoryRevocationList::from_snapshot(read_snapshot(&path)?);
+        Ok(FileRevocationList {
+            path,
+            list,
+            saving: Mutex::new(()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn snapshot(&self) -> RevocationSnapshot {
+        self.list.snapshot()
+    }
+
+    // Replaces the revocations in memory with those currently stored in the file.
+    pub fn reload(&self) -> io::Result<()> {
+        let _saving = self.saving.lock().unwrap_or_else(PoisonError::into_inner);
+        self.list.replace(read_snapshot(&self.path)?);
+        Ok(())
+    }
+
+    pub fn revoke_id(&self, id: impl Into<String>) -> io::Result<()> {
+        let id = id.into();
+        self.update(|snapshot| {
+            snapshot.ids.insert(id.clone());
+        })
+    }
+
+    pub fn revoke_subject(&self, subject: &str, before: DateTime<Utc>) -> io::Result<()> {
+        self.update(|snapshot| snapshot.revoke_subject(subject, before))
+    }
+
+    pub fn revoke_issuer(&self, issuer: impl Into<String>) -> io::Result<()> {
+        let issuer = issuer.into();
+        self.update(|snapshot| {
+            snapshot.issuers.insert(issuer.clone());
+        })
+    }
+
+    // Applies `revoke` in memory, then to the stored snapshot under the lock file, and merges the result into
+    // memory. If the file cannot be updated, the revocation only lasts until the next `reload`.
+    fn update(&self, revoke: impl Fn(&mut RevocationSnapshot)) -> io::Result<()> {
+        // held before touching memory, so no `reload` replaces the revocation before it is stored.
+        let _saving = self.saving.lock().unwrap_or_else(PoisonError::into_inner);
+        revoke(&mut self.list.write());
+        let lock = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(sibling(&self.path, ".lock"))?;
+        lock.lock()?;
+        let mut snapshot = read_snapshot(&self.path)?;
+        revoke(&mut snapshot);
+        write_snapshot(&self.path, &snapshot)?;
+        // merged rather than replaced, so revocations that could not be stored earlier are kept.
+        self.list.write().merge(snapshot);
+        Ok(())
+    }
+}
+
+impl RevocationList for FileRevocationList {
+    fn is_id_revoked(&self, id: &str) -> bool {
+        self.list.is_id_revoked(id)
+    }
+
+    fn subject_revoked_before(&self, subject: &str) -> Option<DateTime<Utc>> {
+        self.list.subject_revoked_before(subject)
+    }
+
+    fn is_issuer_revoked(&self, issuer: &str) -> bool {
+        self.list.is_issuer_revoked(issuer)
+    }
+}
+
+fn read_snapshot(path: &Path) -> io::Result<RevocationSnapshot> {
+    match fs::read(path) {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(RevocationSnapshot::default()),
+        Err(err) => Err(err),
+    }
+}
+
+// Replaces the file at `path` with `snapshot` through a temporary file unique to this call.
+fn write_snapshot(path: &Path, snapshot: &RevocationSnapshot) -> io::Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let temp = sibling(path, &format!(".{}-{}.tmp", std::process::id(), WRITES.fetch_add(1, Ordering::Relaxed)));
+    let result = File::create_new(&temp)
+        .and_then(|mut file| {
+            file.write_all(&serde_json::to_vec(snapshot)?)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+// Returns the path of `path` with `suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut sibling = path.as_os_str().to_owned();
+    sibling.push(suffix);
+    sibling.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn claims() -> RegisteredClaims {
+        RegisteredClaims {
+            issuer: "https://issuer.example.com".to_string(),
+            subject: "alice".to_string(),
+            id: "4f1g23a12aa".to_string(),
+            issued_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn revokes_by_id_subject_and_issuer() {
+        let list = MemoryRevocationList::new();
+        assert_eq!(list.check(&claims()), Ok(()));
+
+        list.revoke_subject("alice", Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(list.check(&claims()), Ok(()));
+        list.revoke_subject("alice", Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 1).unwrap());
+        assert_eq!(list.check(&claims()), Err(ValidationError::TokenRevoked(Claim::Subject)));
+        list.revoke_subject("alice", Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(list.check(&claims()), Err(ValidationError::TokenRevoked(Claim::Subject)));
+
+        let mut claims = claims();
+        claims.subject = "bob".to_string();
+        list.revoke_subject("bob", Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(list.check(&claims), Ok(()));
+        claims.issued_at = None;
+        assert_eq!(list.check(&claims), Err(ValidationError::TokenRevoked(Claim::Subject)));
+
+        claims.subject = String::new();
+        list.revoke_issuer("https://issuer.example.com");
+        assert_eq!(list.check(&claims), Err(ValidationError::TokenRevoked(Claim::Issuer)));
+        list.revoke_id("4f1g23a12aa");
+        assert_eq!(list.check(&claims), Err(ValidationError::TokenRevoked(Claim::Id)));
+    }
+
+    #[test]
+    fn persists_snapshots_to_a_file() {
+        let path = std::env::temp_dir().join(format!("jwt-claims-revocations-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let list = FileRevocationList::open(&path).unwrap();
+        assert_eq!(list.snapshot(), RevocationSnapshot::default());
+        list.revoke_id("4f1g23a12aa").unwrap();
+        list.revoke_subject("alice", Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()).unwrap();
+        list.revoke_issuer("https://old.example.com").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"ids":["4f1g23a12aa"],"subjects":{"alice":1704067200},"issuers":["https://old.example.com"]}"#
+        );
+
+        let reader = FileRevocationList::open(&path).unwrap();
+        assert_eq!(reader.snapshot(), list.snapshot());
+        list.revoke_id("5a2b").unwrap();
+        assert!(!reader.is_id_revoked("5a2b"));
+        reader.reload().unwrap();
+        assert!(reader.is_id_revoked("5a2b"));
+
+        // revoking merges with what other writers stored since, instead of overwriting it.
+        reader.revoke_issuer("https://older.example.com").unwrap();
+        list.revoke_id("6c3d").unwrap();
+        assert!(list.is_issuer_revoked("https://older.example.com"));
+        assert_eq!(read_snapshot(&path).unwrap(), list.snapshot());
+        assert_eq!(read_snapshot(&path).unwrap().ids.len(), 3);
+
+        std::fs::write(&path, "{").unwrap();
+        assert_eq!(reader.reload().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        // a revocation that cannot be stored still applies in memory.
+        assert!(reader.revoke_id("7e4f").is_err());
+        assert!(reader.is_id_revoked("7e4f"));
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(sibling(&path, ".lock")).unwrap();
+    }
+
+    #[test]
+    fn keeps_concurrent_revocations() {
+        let path = std::env::temp_dir().join(format!("jwt-claims-concurrent-revocations-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let list = FileRevocationList::open(&path).unwrap();
+
+        std::thread::scope(|scope| {
+            for writer in ["a", "b"] {
+                let list = &list;
+                scope.spawn(move || {
+                    for i in 0..20 {
+                        list.revoke_id(format!("{writer}-{i}")).unwrap();
+                        assert!((0..=i).all(|i| list.is_id_revoked(&format!("{writer}-{i}"))), "{writer}-{i}");
+                    }
+                });
+            }
+        });
+        assert_eq!(list.snapshot().ids.len(), 40);
+        assert_eq!(read_snapshot(&path).unwrap(), list.snapshot());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(sibling(&path, ".lock")).unwrap();
+    }
+
+    #[test]
+    fn validation_rejects_revoked_tokens() {
+        let revocations = Arc::new(MemoryRevocationList::new());
+        let validation = crate::Validation::new().with_revocation_list(revocations.clone()).with_collect_all(true);
+        let mut claims = claims();
+        claims.expires_at = Some(Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap());
+        let clock = crate::FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 0, 30, 0).unwrap());
+        assert_eq!(validation.validate_at(&claims, &clock), Ok(()));
+
+        revocations.revoke_id("4f1g23a12aa");
+        assert_eq!(validation.validate_at(&claims, &clock), Err(ValidationError::TokenRevoked(Claim::Id)));
+        let err = validation
+            .with_issuer("https://other.example.com")
+            .validate_at(&claims, &clock)
+            .unwrap_err();
+        let reasons: Vec<_> = err.errors().iter().map(ValidationError::reason).collect();
+        assert_eq!(reasons, ["invalid_issuer", "token_revoked"]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+// Clock is the source of the current time used when validating the time-based claims.
+//
+// Validation against `SystemClock` is the normal case; `FixedClock` and `OffsetClock` allow
+// deterministic tests and checking tokens against a historical point in time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+// SystemClock reads the current time from the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// FixedClock always reports the same point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+// OffsetClock shifts the time reported by another clock by a constant offset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OffsetClock<C = SystemClock> {
+    pub inner: C,
+    pub offset: Duration,
+}
+
+impl<C> OffsetClock<C> {
+    pub fn new(inner: C, offset: Duration) -> Self {
+        OffsetClock { inner, offset }
+    }
+}
+
+impl<C: Clock> Clock for OffsetClock<C> {
+    fn now(&self) -> DateTime<Utc> {
+        self.inner.now() + self.offset
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+// Leeway is the clock skew tolerated when comparing the time-based claims against the current time.
+//
+// `expires_at` is added to the `exp` claim, while `not_before` is subtracted from the `nbf` and `iat` claims,
+// so a token is accepted slightly after it expired and slightly before it becomes valid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Leeway {
+    // tolerance applied to the `exp` claim.
+    pub expires_at: Duration,
+    // tolerance applied to the `nbf` and `iat` claims.
+    pub not_before: Duration,
+}
+
+impl Leeway {
+    // Creates a leeway that applies the same tolerance to all time-based claims.
+    pub fn new(leeway: Duration) -> Self {
+        Leeway {
+            expires_at: leeway,
+            not_before: leeway,
+        }
+    }
+
+    // Creates a leeway of `secs` seconds for all time-based claims.
+    pub fn seconds(secs: i64) -> Self {
+        Self::new(Duration::seconds(secs))
+    }
+
+    pub fn with_expires_at(mut self, leeway: Duration) -> Self {
+        self.expires_at = leeway;
+        self
+    }
+
+    pub fn with_not_before(mut self, leeway: Duration) -> Self {
+        self.not_before = leeway;
+        self
+    }
+}
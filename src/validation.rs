@@ -0,0 +1,438 @@
+use std::{fmt, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{jws::Header, Clock, Leeway, RegisteredClaims, ReplayStore, RevocationList, SystemClock, ValidationError};
+
+// Claim names one of the Registered Claim Names. See https://datatracker.ietf.org/doc/html/rfc7519#section-4.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Claim {
+    Issuer,
+    Subject,
+    Audience,
+    ExpiresAt,
+    NotBefore,
+    IssuedAt,
+    Id,
+}
+
+impl Claim {
+    // Returns the name the claim is serialized under, e.g. `exp`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Claim::Issuer => "iss",
+            Claim::Subject => "sub",
+            Claim::Audience => "aud",
+            Claim::ExpiresAt => "exp",
+            Claim::NotBefore => "nbf",
+            Claim::IssuedAt => "iat",
+            Claim::Id => "jti",
+        }
+    }
+
+    // Reports whether `claims` carries a value for this claim. A zero timestamp counts as absent,
+    // the same way the `verify_*` helpers of `RegisteredClaims` treat it.
+    pub fn is_present(&self, claims: &RegisteredClaims) -> bool {
+        let ts = |t: &Option<DateTime<Utc>>| t.is_some_and(|t| t.timestamp() != 0);
+        match self {
+            Claim::Issuer => !claims.issuer.is_empty(),
+            Claim::Subject => !claims.subject.is_empty(),
+            Claim::Audience => !claims.audience.is_empty(),
+            Claim::ExpiresAt => ts(&claims.expires_at),
+            Claim::NotBefore => ts(&claims.not_before),
+            Claim::IssuedAt => ts(&claims.issued_at),
+            Claim::Id => !claims.id.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for Claim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Validation is a policy that a `RegisteredClaims` must satisfy, built up front and applied with `validate`.
+//
+// `Validation::new()` requires the `exp` claim and otherwise only checks the time-based claims that are present.
+// Configuring issuers, audiences or a subject makes the corresponding claim mandatory. A maximum age makes `iat`
+// mandatory, and a replay store makes `jti` and `exp` mandatory.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub required: Vec<Claim>,
+    pub issuers: Vec<String>,
+    pub audiences: Vec<String>,
+    pub subject: Option<String>,
+    pub id: Option<String>,
+    pub leeway: Leeway,
+    pub max_age: Option<Duration>,
+    // report every failure as `ValidationError::Multiple` instead of stopping at the first one.
+    pub collect_all: bool,
+    // the expected `typ` Header Parameter, if any.
+    pub token_type: Option<String>,
+    // the extension Header Parameters the caller understands and may appear in `crit`.
+    pub critical_headers: Vec<String>,
+    // the number of JWE layers a Nested JWT may wrap around its JWS.
+    pub max_nesting_depth: usize,
+    // the store that accepts each `jti` only once, if any.
+    pub replay_store: Option<Arc<dyn ReplayStore>>,
+    // the list of revoked tokens to reject, if any.
+    pub revocations: Option<Arc<dyn RevocationList>>,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Validation {
+            required: vec![Claim::ExpiresAt],
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            subject: None,
+            id: None,
+            leeway: Leeway::default(),
+            max_age: None,
+            collect_all: false,
+            token_type: None,
+            critical_headers: Vec::new(),
+            max_nesting_depth: 1,
+            replay_store: None,
+            revocations: None,
+        }
+    }
+}
+
+// Policies are equal when they check the same things, and share the same replay store and revocation list if they
+// have them.
+impl PartialEq for Validation {
+    fn eq(&self, other: &Self) -> bool {
+        let Validation {
+            required,
+            issuers,
+            audiences,
+            subject,
+            id,
+            leeway,
+            max_age,
+            collect_all,
+            token_type,
+            critical_headers,
+            max_nesting_depth,
+            replay_store,
+            revocations,
+        } = self;
+        *required == other.required
+            && *issuers == other.issuers
+            && *audiences == other.audiences
+            && *subject == other.subject
+            && *id == other.id
+            && *leeway == other.leeway
+            && *max_age == other.max_age
+            && *collect_all == other.collect_all
+            && *token_type == other.token_type
+            && *critical_headers == other.critical_headers
+            && *max_nesting_depth == other.max_nesting_depth
+            && same(replay_store, &other.replay_store)
+            && same(revocations, &other.revocations)
+    }
+}
+
+impl Eq for Validation {}
+
+fn same<T: ?Sized>(a: &Option<Arc<T>>, b: &Option<Arc<T>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+impl Validation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces the set of claims that must be present.
+    pub fn with_required_claims(mut self, claims: impl IntoIterator<Item = Claim>) -> Self {
+        self.required = claims.into_iter().collect();
+        self
+    }
+
+    // Adds an issuer to the allowed set. The `iss` claim must match one of them.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+
+    // Adds an audience to the accepted set. At least one `aud` value must match one of them.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    // Sets the expected `sub` claim.
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    // Sets the expected `jti` claim.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_leeway(mut self, leeway: Leeway) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    // Rejects tokens whose `iat` claim is older than `max_age`.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    // Collects every failure into a single `ValidationError::Multiple` rather than returning the first one.
+    pub fn with_collect_all(mut self, collect_all: bool) -> Self {
+        self.collect_all = collect_all;
+        self
+    }
+
+    // Requires the `typ` Header Parameter to name `token_type`, e.g. `JWT` or `at+jwt`.
+    pub fn with_token_type(mut self, token_type: impl Into<String>) -> Self {
+        self.token_type = Some(token_type.into());
+        self
+    }
+
+    // Declares an extension Header Parameter as understood, so tokens may list it in `crit`.
+    pub fn with_critical_header(mut self, name: impl Into<String>) -> Self {
+        self.critical_headers.push(name.into());
+        self
+    }
+
+    // Allows Nested JWTs to be encrypted up to `depth` times around the signed token. Defaults to 1; 0 rejects
+    // every Nested JWT.
+    pub fn with_max_nesting_depth(mut self, depth: usize) -> Self {
+        self.max_nesting_depth = depth;
+        self
+    }
+
+    // Accepts each token only once, recording its `jti` in `store` once every other check has passed. To share
+    // the store between policies, pass an `Arc` clone of it.
+    pub fn with_replay_store(mut self, store: impl ReplayStore + 'static) -> Self {
+        self.replay_store = Some(Arc::new(store));
+        self
+    }
+
+    // Rejects the tokens that `revocations` lists as revoked. To revoke tokens while they are being validated, pass
+    // an `Arc` clone of the list.
+    pub fn with_revocation_list(mut self, revocations: impl RevocationList + 'static) -> Self {
+        self.revocations = Some(Arc::new(revocations));
+        self
+    }
+
+    // Checks the JOSE Header of a token: its `crit` parameter and, if configured, its `typ`.
+    pub fn validate_header(&self, header: &Header) -> Result<(), ValidationError> {
+        self.check_header(header.token_type.as_deref(), |understood| header.verify_critical(understood))
+    }
+
+    // Like `validate_header`, for the JOSE Header of an encrypted token.
+    #[cfg(feature = "jwe")]
+    pub(crate) fn validate_jwe_header(&self, header: &crate::jwe::Header) -> Result<(), ValidationError> {
+        self.check_header(header.token_type.as_deref(), |understood| header.verify_critical(understood))
+    }
+
+    fn check_header(
+        &self,
+        token_type: Option<&str>,
+        verify_critical: impl FnOnce(&[String]) -> Result<(), ValidationError>,
+    ) -> Result<(), ValidationError> {
+        verify_critical(&self.critical_headers)?;
+        if let Some(ref typ) = self.token_type {
+            if !crate::jws::type_matches(token_type, typ) {
+                return Err(ValidationError::InvalidTokenType(token_type.unwrap_or_default().to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self, claims: &RegisteredClaims) -> Result<(), ValidationError> {
+        self.validate_at(claims, &SystemClock)
+    }
+
+    // Like `validate`, but reads the current time from `clock`.
+    //
+    // With a replay store, a successful validation consumes the token, so claims must only be validated once
+    // their signature has been verified, as `jws::verify` does.
+    pub fn validate_at(&self, claims: &RegisteredClaims, clock: &dyn Clock) -> Result<(), ValidationError> {
+        let mut errors = self.errors(claims, clock);
+        match errors.len() {
+            0 => match self.replay_store {
+                Some(ref store) => store.check_at(claims, &self.leeway, clock),
+                None => Ok(()),
+            },
+            1 => Err(errors.remove(0)),
+            _ if self.collect_all => Err(ValidationError::Multiple(errors)),
+            _ => Err(errors.remove(0)),
+        }
+    }
+
+    // Runs every check and returns all failures, in the order `validate_at` reports them.
+    fn errors(&self, claims: &RegisteredClaims, clock: &dyn Clock) -> Vec<ValidationError> {
+        let mut errors: Vec<_> = self
+            .required_claims()
+            .into_iter()
+            .filter(|claim| !claim.is_present(claims))
+            .map(ValidationError::MissingRequiredClaim)
+            .collect();
+
+        let now = clock.now();
+        errors.extend(claims.time_errors(now, &self.leeway));
+
+        if let (Some(max_age), Some(issued_at)) = (self.max_age, claims.issued_at) {
+            let age = now - issued_at;
+            // a maximum age too large to represent is never exceeded.
+            if max_age.checked_add(&self.leeway.expires_at).is_some_and(|max_age| age > max_age) {
+                errors.push(ValidationError::TokenTooOld { issued_at, age });
+            }
+        }
+        // Absent claims were already reported as missing above.
+        if !self.issuers.is_empty() && !claims.issuer.is_empty() && !self.issuers.iter().any(|iss| claims.verify_issuer(iss, true)) {
+            errors.push(ValidationError::InvalidIssuer(claims.issuer.clone()));
+        }
+        if !self.audiences.is_empty() && !claims.audience.is_empty() && !self.audiences.iter().any(|aud| claims.verify_audience(aud, true)) {
+            errors.push(ValidationError::InvalidAudience(claims.audience.clone()));
+        }
+        if let Some(ref sub) = self.subject {
+            if !claims.subject.is_empty() && !claims.verify_subject(sub, true) {
+                errors.push(ValidationError::InvalidSubject(claims.subject.clone()));
+            }
+        }
+        if let Some(ref id) = self.id {
+            if !claims.id.is_empty() && !claims.verify_id(id, true) {
+                errors.push(ValidationError::InvalidId(claims.id.clone()));
+            }
+        }
+        if let Some(Err(err)) = self.revocations.as_ref().map(|revocations| revocations.check(claims)) {
+            errors.push(err);
+        }
+        errors
+    }
+
+    // Returns the claims required explicitly or by another check, each once.
+    fn required_claims(&self) -> Vec<Claim> {
+        let implied = [
+            (!self.issuers.is_empty()).then_some(Claim::Issuer),
+            self.subject.as_ref().map(|_| Claim::Subject),
+            self.id.as_ref().map(|_| Claim::Id),
+            (!self.audiences.is_empty()).then_some(Claim::Audience),
+            self.max_age.map(|_| Claim::IssuedAt),
+            self.replay_store.as_ref().map(|_| Claim::Id),
+            self.replay_store.as_ref().map(|_| Claim::ExpiresAt),
+        ];
+        let mut required = Vec::new();
+        for claim in self.required.iter().copied().chain(implied.into_iter().flatten()) {
+            if !required.contains(&claim) {
+                required.push(claim);
+            }
+        }
+        required
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+    use crate::FixedClock;
+
+    fn claims() -> RegisteredClaims {
+        RegisteredClaims {
+            issuer: "https://issuer.example.com".to_string(),
+            subject: "alice".to_string(),
+            audience: "api".into(),
+            expires_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap()),
+            issued_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn clock() -> FixedClock {
+        FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 0, 30, 0).unwrap())
+    }
+
+    #[test]
+    fn applies_the_whole_policy() {
+        let validation = Validation::new()
+            .with_issuer("https://other.example.com")
+            .with_issuer("https://issuer.example.com")
+            .with_audience("api")
+            .with_subject("alice")
+            .with_max_age(Duration::hours(1));
+        assert!(validation.validate_at(&claims(), &clock()).is_ok());
+
+        let err = validation.clone().with_subject("bob").validate_at(&claims(), &clock());
+        assert_eq!(err, Err(ValidationError::InvalidSubject("alice".to_string())));
+
+        let err = Validation::new().with_audience("web").validate_at(&claims(), &clock());
+        assert_eq!(err, Err(ValidationError::InvalidAudience("api".into())));
+
+        let err = Validation::new()
+            .with_issuer("https://other.example.com")
+            .validate_at(&claims(), &clock());
+        assert_eq!(err, Err(ValidationError::InvalidIssuer("https://issuer.example.com".to_string())));
+
+        let err = Validation::new().with_max_age(Duration::minutes(10)).validate_at(&claims(), &clock());
+        assert_eq!(
+            err,
+            Err(ValidationError::TokenTooOld {
+                issued_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                age: Duration::minutes(30),
+            })
+        );
+    }
+
+    #[test]
+    fn enforces_required_claims() {
+        let mut claims = claims();
+        claims.expires_at = None;
+        let err = Validation::new().validate_at(&claims, &clock());
+        assert!(matches!(err, Err(ValidationError::MissingRequiredClaim(Claim::ExpiresAt))));
+
+        let validation = Validation::new().with_required_claims([Claim::Id]);
+        let err = validation.validate_at(&claims, &clock());
+        assert!(matches!(err, Err(ValidationError::MissingRequiredClaim(Claim::Id))));
+
+        // a claim required both explicitly and by another check is reported once.
+        let err = validation.clone().with_id("jti").with_collect_all(true).validate_at(&claims, &clock());
+        assert_eq!(err, Err(ValidationError::MissingRequiredClaim(Claim::Id)));
+
+        claims.id = "jti".to_string();
+        assert!(validation.validate_at(&claims, &clock()).is_ok());
+
+        claims.issued_at = None;
+        let err = validation.with_max_age(Duration::hours(1)).validate_at(&claims, &clock());
+        assert!(matches!(err, Err(ValidationError::MissingRequiredClaim(Claim::IssuedAt))));
+    }
+
+    #[test]
+    fn collects_every_failure() {
+        let mut claims = claims();
+        claims.expires_at = Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 29, 0).unwrap());
+        let validation = Validation::new()
+            .with_issuer("https://other.example.com")
+            .with_audience("web")
+            .with_id("nonce");
+
+        let err = validation.validate_at(&claims, &clock()).unwrap_err();
+        assert_eq!(err, ValidationError::MissingRequiredClaim(Claim::Id));
+
+        let err = validation.with_collect_all(true).validate_at(&claims, &clock()).unwrap_err();
+        let reasons: Vec<_> = err.errors().iter().map(ValidationError::reason).collect();
+        assert_eq!(reasons, ["missing_required_claim", "token_expired", "invalid_issuer", "invalid_audience"]);
+        assert_eq!(
+            err.to_string(),
+            "token is invalid: token is missing required claim `jti`; token is expired by 60s; \
+             token has invalid issuer `https://issuer.example.com`; token has invalid audience `api`"
+        );
+    }
+}